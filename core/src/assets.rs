@@ -0,0 +1,113 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::manifest::hash_file;
+use crate::project::IconProject;
+
+// Replacement files imported into a project live here, named by their content hash so
+// the same file is only stored once and the project directory can be shared as it is.
+pub const ASSETS_DIR_NAME: &str = "assets";
+
+// Copies `source` into the asset store of the project in `project_dir`, returns its path
+// relative to the project directory
+pub fn import_asset(project_dir: &Path, source: &Path) -> Result<PathBuf> {
+    let hash = hash_file(source)?;
+    let file_name = match source.extension() {
+        Some(ext) => format!("{}.{}", hash, ext.to_string_lossy().to_lowercase()),
+        None => hash,
+    };
+    let relative = Path::new(ASSETS_DIR_NAME).join(&file_name);
+    let dest = project_dir.join(&relative);
+    if dest.is_file() {
+        return Ok(relative);
+    }
+
+    let assets_dir = project_dir.join(ASSETS_DIR_NAME);
+    fs::create_dir_all(&assets_dir)
+        .with_context(|| format!("Failed to create {}", assets_dir.display()))?;
+    // Copied under a temporary name first, a file named by its hash is always complete
+    let partial = assets_dir.join(format!(".{}.partial", file_name));
+    fs::copy(source, &partial)
+        .with_context(|| format!("Failed to copy {} to {}", source.display(), partial.display()))?;
+    fs::rename(&partial, &dest)
+        .with_context(|| format!("Failed to move {} to {}", partial.display(), dest.display()))?;
+    log::info!("Imported {} as {}", source.display(), relative.display());
+    Ok(relative)
+}
+
+// Imports every replacement that isn't in the store yet and points the project at the
+// stored copy. `imported` maps originals to their stored copies across calls, so a copy
+// of the project made before an import doesn't need the original any more. Returns how
+// many paths changed.
+pub fn import_assets(
+    project: &mut IconProject,
+    project_dir: &Path,
+    imported: &mut BTreeMap<PathBuf, PathBuf>,
+) -> Result<usize> {
+    let mut changed = 0;
+    for path in project.replacement_paths_mut() {
+        if path.is_relative() {
+            continue;
+        }
+        let stored = match imported.get(path.as_path()) {
+            Some(stored) if project_dir.join(stored).is_file() => stored.clone(),
+            _ => import_asset(project_dir, path)?,
+        };
+        imported.insert(path.clone(), stored.clone());
+        *path = stored;
+        changed += 1;
+    }
+    Ok(changed)
+}
+
+// Points replacements at copies imported into the store since the project was taken
+pub fn apply_imports(project: &mut IconProject, imported: &BTreeMap<PathBuf, PathBuf>) {
+    for path in project.replacement_paths_mut() {
+        if let Some(stored) = imported.get(path.as_path()) {
+            *path = stored.clone();
+        }
+    }
+}
+
+// Removes stored files no replacement refers to any more, returns their paths relative
+// to the project directory. Only projects that vendor their assets have a store, and only
+// files named like the store names them are ever removed, anything else in the folder was
+// put there by hand.
+pub fn collect_garbage(project: &IconProject, project_dir: &Path) -> Result<Vec<PathBuf>> {
+    let assets_dir = project_dir.join(ASSETS_DIR_NAME);
+    if !project.vendor_assets || !assets_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let referenced: BTreeSet<PathBuf> = project.replacement_paths().map(|path| project_dir.join(path)).collect();
+    let mut removed = Vec::new();
+    let entries = fs::read_dir(&assets_dir)
+        .with_context(|| format!("Failed to read {}", assets_dir.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() || !is_stored_name(&entry.file_name().to_string_lossy()) {
+            continue;
+        }
+        if referenced.contains(&entry.path()) {
+            continue;
+        }
+        let relative = Path::new(ASSETS_DIR_NAME).join(entry.file_name());
+        fs::remove_file(entry.path())
+            .with_context(|| format!("Failed to remove {}", entry.path().display()))?;
+        log::info!("Removed unused asset {}", relative.display());
+        removed.push(relative);
+    }
+    removed.sort();
+    Ok(removed)
+}
+
+// `<sha256>` or `<sha256>.<lowercase extension>`, as `import_asset` names files
+fn is_stored_name(name: &str) -> bool {
+    let (hash, ext) = name.split_once('.').unwrap_or((name, ""));
+    hash.len() == 64
+        && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+        && !ext.contains('.')
+        && ext.chars().all(|c| !c.is_uppercase())
+}
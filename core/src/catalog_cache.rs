@@ -0,0 +1,93 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::icon_catalog::IconVariant;
+use crate::icon_theme::IconThemeDefinition;
+
+// Bump when the cached data changes shape, older caches are then ignored
+const CACHE_VERSION: u32 = 1;
+
+// Scan results of every theme root, reused as long as the theme's files look unchanged
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CatalogCache {
+    version: u32,
+    pub themes: Vec<CachedTheme>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedTheme {
+    pub root: PathBuf,
+    // Modification times of index.theme and every listed directory, by path relative to
+    // the root. Directories that don't exist are recorded as None, so they count as a
+    // change once they are created.
+    pub stamps: BTreeMap<PathBuf, Option<SystemTime>>,
+    pub icons: BTreeMap<String, Vec<IconVariant>>,
+}
+
+impl CatalogCache {
+    // A missing, unreadable or outdated cache is treated as empty
+    pub fn load(path: &Path) -> Self {
+        let Ok(content) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+        match serde_json::from_str::<Self>(&content) {
+            Ok(cache) if cache.version == CACHE_VERSION => cache,
+            Ok(_) => {
+                log::info!("Ignoring catalog cache {} from an older version", path.display());
+                Self::default()
+            }
+            Err(e) => {
+                log::warn!("Ignoring unreadable catalog cache {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let content = serde_json::to_string(self)?;
+        fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    // The cached scan of the theme at `root`, if none of its stamps changed since
+    pub fn fresh_theme(&self, root: &Path, stamps: &BTreeMap<PathBuf, Option<SystemTime>>) -> Option<&CachedTheme> {
+        self.themes
+            .iter()
+            .find(|theme| theme.root == root && theme.stamps == *stamps)
+    }
+
+    // Forces the theme at `root` to be scanned again on the next discovery
+    pub fn invalidate(&mut self, root: &Path) {
+        self.themes.retain(|theme| theme.root != root);
+    }
+
+    pub fn from_themes(themes: Vec<CachedTheme>) -> Self {
+        Self {
+            version: CACHE_VERSION,
+            themes,
+        }
+    }
+}
+
+pub fn theme_stamps(theme: &IconThemeDefinition) -> BTreeMap<PathBuf, Option<SystemTime>> {
+    let mut stamps = BTreeMap::new();
+    let index = PathBuf::from("index.theme");
+    stamps.insert(index.clone(), modified(&theme.root_path.join(&index)));
+    for directory in &theme.directories {
+        let relative = PathBuf::from(&directory.key);
+        stamps.insert(relative.clone(), modified(&theme.root_path.join(&relative)));
+    }
+    stamps
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
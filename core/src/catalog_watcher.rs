@@ -0,0 +1,222 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::Result;
+use notify::event::ModifyKind;
+use notify::{Config, Event, EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::catalog_cache::CatalogCache;
+use crate::icon_catalog::IconCatalog;
+use crate::index_theme::{IndexTheme, ICON_THEME_GROUP};
+use crate::search_paths::{candidate_search_paths, load_extra_paths};
+
+// Package installs touch thousands of files, wait for them to settle before rescanning
+const SETTLE: Duration = Duration::from_secs(1);
+// How often search paths we can't watch are scanned for changes
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+// Watches the icon search paths and rebuilds the catalog when themes below them change.
+// Only the changed themes are scanned again, the others come from the cache. Watching
+// stops when this is dropped.
+pub struct CatalogWatcher {
+    _watches: Arc<Mutex<Watches>>,
+}
+
+impl CatalogWatcher {
+    pub fn new(cache: CatalogCache, on_change: impl Fn(IconCatalog, &CatalogCache) + Send + 'static) -> Result<Self> {
+        let roots = candidate_search_paths(&load_extra_paths());
+        let (sender, receiver) = mpsc::channel();
+        let mut watches = Watches {
+            watcher: notify::recommended_watcher(handler(sender.clone()))?,
+            poller: None,
+            polled: BTreeSet::new(),
+            missing: BTreeSet::new(),
+            sender,
+        };
+        for root in &roots {
+            watches.watch_root(root);
+        }
+        let watches = Arc::new(Mutex::new(watches));
+        let weak = Arc::downgrade(&watches);
+        thread::spawn(move || run(receiver, roots, weak, cache, on_change));
+        Ok(Self { _watches: watches })
+    }
+}
+
+// Watching whole search paths recursively also takes a watch for every directory that
+// holds no icons, so only the search paths, the themes in them, their top level directories
+// and the icon directories their index.theme lists are watched, none of them recursively.
+// Search paths that don't exist yet are watched through their closest existing parent,
+// search paths that run out of watches are polled.
+struct Watches {
+    watcher: RecommendedWatcher,
+    poller: Option<PollWatcher>,
+    polled: BTreeSet<PathBuf>,
+    missing: BTreeSet<PathBuf>,
+    sender: Sender<Vec<PathBuf>>,
+}
+
+impl Watches {
+    fn watch_root(&mut self, root: &Path) {
+        if !root.is_dir() {
+            self.missing.insert(root.to_path_buf());
+            if let Some(parent) = root.ancestors().skip(1).find(|dir| dir.is_dir()) {
+                if let Err(e) = self.watcher.watch(parent, RecursiveMode::NonRecursive) {
+                    log::warn!("Cannot watch {}: {}", parent.display(), e);
+                }
+            }
+            return;
+        }
+        self.missing.remove(root);
+        if let Err(e) = self.watcher.watch(root, RecursiveMode::NonRecursive) {
+            log::warn!("Cannot watch {}: {}", root.display(), e);
+            self.poll(root);
+            return;
+        }
+        for theme in subdirectories(root) {
+            self.watch_theme(root, &theme);
+        }
+    }
+
+    fn watch_theme(&mut self, root: &Path, theme: &Path) {
+        if self.polled.contains(root) {
+            return;
+        }
+        let mut dirs: BTreeSet<PathBuf> = subdirectories(theme).into_iter().collect();
+        dirs.insert(theme.to_path_buf());
+        if let Ok(index) = IndexTheme::load(&theme.join("index.theme")) {
+            let listed = index.get_list(ICON_THEME_GROUP, "Directories")
+                .into_iter()
+                .chain(index.get_list(ICON_THEME_GROUP, "ScaledDirectories"));
+            dirs.extend(listed.map(|dir| theme.join(dir)).filter(|dir| dir.is_dir()));
+        }
+        for dir in dirs {
+            if let Err(e) = self.watcher.watch(&dir, RecursiveMode::NonRecursive) {
+                log::warn!("Cannot watch {}: {}", dir.display(), e);
+                self.poll(root);
+                return;
+            }
+        }
+    }
+
+    // Starts watching the search paths created since they were last checked, returns the
+    // themes in them
+    fn watch_created_roots(&mut self) -> Vec<PathBuf> {
+        let mut themes = Vec::new();
+        for root in std::mem::take(&mut self.missing) {
+            self.watch_root(&root);
+            if root.is_dir() {
+                themes.extend(subdirectories(&root));
+            }
+        }
+        themes
+    }
+
+    fn poll(&mut self, root: &Path) {
+        if !self.polled.insert(root.to_path_buf()) {
+            return;
+        }
+        if self.poller.is_none() {
+            let config = Config::default().with_poll_interval(POLL_INTERVAL);
+            match PollWatcher::new(handler(self.sender.clone()), config) {
+                Ok(poller) => self.poller = Some(poller),
+                Err(e) => {
+                    log::error!("Cannot poll icon directories: {}", e);
+                    return;
+                }
+            }
+        }
+        if let Some(poller) = &mut self.poller {
+            match poller.watch(root, RecursiveMode::Recursive) {
+                Ok(()) => log::info!("Polling {} for changes", root.display()),
+                Err(e) => log::error!("Cannot poll {}: {}", root.display(), e),
+            }
+        }
+    }
+}
+
+fn handler(sender: Sender<Vec<PathBuf>>) -> impl Fn(notify::Result<Event>) + Send + 'static {
+    move |result: notify::Result<Event>| match result {
+        Ok(event) if is_relevant(&event.kind) => {
+            let _ = sender.send(event.paths);
+        }
+        Ok(_) => {}
+        Err(e) => log::warn!("Icon directory watch error: {}", e),
+    }
+}
+
+fn subdirectories(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+        .map(|entry| entry.path())
+        .collect()
+}
+
+fn run(
+    receiver: Receiver<Vec<PathBuf>>,
+    roots: Vec<PathBuf>,
+    watches: Weak<Mutex<Watches>>,
+    mut cache: CatalogCache,
+    on_change: impl Fn(IconCatalog, &CatalogCache),
+) {
+    while let Ok(paths) = receiver.recv() {
+        let mut changed: BTreeSet<PathBuf> = BTreeSet::new();
+        changed.extend(paths.iter().filter_map(|path| theme_root(&roots, path)));
+        loop {
+            match receiver.recv_timeout(SETTLE) {
+                Ok(paths) => changed.extend(paths.iter().filter_map(|path| theme_root(&roots, path))),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+        let Some(watches) = watches.upgrade() else {
+            return;
+        };
+        changed.extend(watches.lock().unwrap().watch_created_roots());
+        if changed.is_empty() {
+            continue;
+        }
+
+        // Changes deep inside a directory don't show up in its mtime, so drop the
+        // themes we got events for instead of relying on the stamps alone
+        for root in &changed {
+            cache.invalidate(root);
+        }
+        // New themes and directories need watches of their own
+        for theme in changed.iter().filter(|theme| theme.is_dir()) {
+            if let Some(root) = theme.parent() {
+                watches.lock().unwrap().watch_theme(root, theme);
+            }
+        }
+        drop(watches);
+        log::info!("Icon themes changed on disk: {:?}", changed);
+        match IconCatalog::discover_with_cache(None, &cache) {
+            Ok((catalog, updated)) => {
+                cache = updated;
+                on_change(catalog, &cache);
+            }
+            Err(e) => log::error!("Failed to update icon catalog: {:?}", e),
+        }
+    }
+}
+
+fn is_relevant(kind: &EventKind) -> bool {
+    !matches!(kind, EventKind::Access(_) | EventKind::Modify(ModifyKind::Metadata(_)))
+}
+
+// The theme directory `path` belongs to, i.e. the first component below a search path
+fn theme_root(roots: &[PathBuf], path: &Path) -> Option<PathBuf> {
+    roots.iter().find_map(|root| {
+        let first = path.strip_prefix(root).ok()?.components().next()?;
+        Some(root.join(first))
+    })
+}
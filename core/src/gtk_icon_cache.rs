@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+
+use crate::icon_catalog::IconFormat;
+
+pub const CACHE_FILE_NAME: &str = "icon-theme.cache";
+
+const NO_OFFSET: u32 = 0xFFFF_FFFF;
+const HAS_SUFFIX_XPM: u16 = 1;
+const HAS_SUFFIX_SVG: u16 = 2;
+const HAS_SUFFIX_PNG: u16 = 4;
+
+// Reader for the icon-theme.cache files written by gtk-update-icon-cache. Everything is
+// big endian. The header points at a hash table of icons and at the list of directory
+// names; every icon lists the directories it has an image in and the image's suffixes.
+#[derive(Debug)]
+pub struct GtkIconCache {
+    data: Vec<u8>,
+}
+
+// One image of an icon. The formats are in file name order, like a directory listing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheEntry {
+    pub name: String,
+    pub directory: String,
+    pub formats: Vec<IconFormat>,
+}
+
+impl GtkIconCache {
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(data).with_context(|| format!("Invalid icon cache {}", path.display()))
+    }
+
+    pub fn parse(data: Vec<u8>) -> Result<Self> {
+        let cache = Self { data };
+        let major = cache.u16_at(0)?;
+        if major != 1 {
+            bail!("Unsupported cache version {}", major);
+        }
+        Ok(cache)
+    }
+
+    pub fn directories(&self) -> Result<Vec<String>> {
+        let list = self.u32_at(8)?;
+        let count = self.u32_at(list)?;
+        (0..count)
+            .map(|i| self.string_at(self.u32_at(list + 4 + i * 4)?))
+            .collect()
+    }
+
+    pub fn entries(&self) -> Result<Vec<CacheEntry>> {
+        let directories = self.directories()?;
+        let hash = self.u32_at(4)?;
+        let buckets = self.u32_at(hash)?;
+        let mut entries = Vec::new();
+        // Every icon takes at least 12 bytes, more steps than that means a chain loops
+        let mut budget = self.data.len() / 12;
+        for bucket in 0..buckets {
+            let mut icon = self.u32_at(hash + 4 + bucket * 4)?;
+            while icon != NO_OFFSET {
+                if budget == 0 {
+                    bail!("Icon chains loop");
+                }
+                budget -= 1;
+                let name = self.string_at(self.u32_at(icon + 4)?)?;
+                let images = self.u32_at(icon + 8)?;
+                for i in 0..self.u32_at(images)? {
+                    let image = images + 4 + i * 8;
+                    let index = self.u16_at(image)? as usize;
+                    let flags = self.u16_at(image + 2)?;
+                    let Some(directory) = directories.get(index) else {
+                        bail!("Icon {} refers to directory {} of {}", name, index, directories.len());
+                    };
+                    entries.push(CacheEntry {
+                        name: name.clone(),
+                        directory: directory.clone(),
+                        formats: formats(flags),
+                    });
+                }
+                icon = self.u32_at(icon)?;
+            }
+        }
+        Ok(entries)
+    }
+
+    fn bytes_at(&self, offset: u32, len: usize) -> Result<&[u8]> {
+        let start = offset as usize;
+        match self.data.get(start..start + len) {
+            Some(bytes) => Ok(bytes),
+            None => bail!("Offset {} is past the end of the cache", offset),
+        }
+    }
+
+    fn u16_at(&self, offset: u32) -> Result<u16> {
+        let bytes = self.bytes_at(offset, 2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32_at(&self, offset: u32) -> Result<u32> {
+        let bytes = self.bytes_at(offset, 4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn string_at(&self, offset: u32) -> Result<String> {
+        let rest = self.data.get(offset as usize..).unwrap_or_default();
+        let Some(len) = rest.iter().position(|&b| b == 0) else {
+            bail!("Unterminated string at {}", offset);
+        };
+        Ok(String::from_utf8_lossy(&rest[..len]).into_owned())
+    }
+}
+
+// Builds a cache in the layout gtk-update-icon-cache uses: header, hash table, icons,
+// image lists, strings and finally the directory list. Image data isn't embedded.
+pub fn encode(directories: &[String], entries: &[CacheEntry]) -> Result<Vec<u8>> {
+    let mut images: BTreeMap<&str, Vec<(u16, u16)>> = BTreeMap::new();
+    for entry in entries {
+        let Some(index) = directories.iter().position(|d| *d == entry.directory) else {
+            bail!("Icon {} is in unknown directory {}", entry.name, entry.directory);
+        };
+        images.entry(&entry.name).or_default().push((index as u16, flags(&entry.formats)));
+    }
+
+    let bucket_count = (images.len() / 3).max(1) as u32 | 1;
+    let mut buckets: Vec<Vec<&str>> = vec![Vec::new(); bucket_count as usize];
+    for name in images.keys() {
+        buckets[(icon_name_hash(name) % bucket_count) as usize].push(name);
+    }
+
+    let hash_offset = 12u32;
+    let icons_offset = hash_offset + 4 + bucket_count * 4;
+    let mut icon_offsets: BTreeMap<&str, u32> = BTreeMap::new();
+    for (i, name) in buckets.iter().flatten().enumerate() {
+        icon_offsets.insert(name, icons_offset + i as u32 * 12);
+    }
+    let mut offset = icons_offset + images.len() as u32 * 12;
+    let mut image_list_offsets: BTreeMap<&str, u32> = BTreeMap::new();
+    for (name, list) in &images {
+        image_list_offsets.insert(name, offset);
+        offset += 4 + list.len() as u32 * 8;
+    }
+    let mut name_offsets: BTreeMap<&str, u32> = BTreeMap::new();
+    for name in images.keys() {
+        name_offsets.insert(name, offset);
+        offset += name.len() as u32 + 1;
+    }
+    let mut directory_offsets = Vec::new();
+    for directory in directories {
+        directory_offsets.push(offset);
+        offset += directory.len() as u32 + 1;
+    }
+    let directory_list_offset = offset.next_multiple_of(4);
+
+    let mut data = Vec::new();
+    data.extend_from_slice(&1u16.to_be_bytes());
+    data.extend_from_slice(&0u16.to_be_bytes());
+    data.extend_from_slice(&hash_offset.to_be_bytes());
+    data.extend_from_slice(&directory_list_offset.to_be_bytes());
+    data.extend_from_slice(&bucket_count.to_be_bytes());
+    for bucket in &buckets {
+        let first = bucket.first().map(|name| icon_offsets[name]).unwrap_or(NO_OFFSET);
+        data.extend_from_slice(&first.to_be_bytes());
+    }
+    for bucket in &buckets {
+        for (i, name) in bucket.iter().enumerate() {
+            let chain = bucket.get(i + 1).map(|next| icon_offsets[next]).unwrap_or(NO_OFFSET);
+            data.extend_from_slice(&chain.to_be_bytes());
+            data.extend_from_slice(&name_offsets[name].to_be_bytes());
+            data.extend_from_slice(&image_list_offsets[name].to_be_bytes());
+        }
+    }
+    for list in images.values() {
+        data.extend_from_slice(&(list.len() as u32).to_be_bytes());
+        for (directory, flags) in list {
+            data.extend_from_slice(&directory.to_be_bytes());
+            data.extend_from_slice(&flags.to_be_bytes());
+            data.extend_from_slice(&0u32.to_be_bytes());
+        }
+    }
+    for name in images.keys() {
+        data.extend_from_slice(name.as_bytes());
+        data.push(0);
+    }
+    for directory in directories {
+        data.extend_from_slice(directory.as_bytes());
+        data.push(0);
+    }
+    data.resize(directory_list_offset as usize, 0);
+    data.extend_from_slice(&(directories.len() as u32).to_be_bytes());
+    for offset in directory_offsets {
+        data.extend_from_slice(&offset.to_be_bytes());
+    }
+    Ok(data)
+}
+
+// GTK's hash over the name's bytes read as signed chars
+fn icon_name_hash(name: &str) -> u32 {
+    let mut bytes = name.bytes().map(|b| b as i8 as u32);
+    let Some(first) = bytes.next() else {
+        return 0;
+    };
+    bytes.fold(first, |h, b| (h << 5).wrapping_sub(h).wrapping_add(b))
+}
+
+fn flags(formats: &[IconFormat]) -> u16 {
+    formats.iter().fold(0, |flags, format| {
+        flags
+            | match format {
+                IconFormat::Png => HAS_SUFFIX_PNG,
+                IconFormat::Svg => HAS_SUFFIX_SVG,
+                IconFormat::Xpm => HAS_SUFFIX_XPM,
+                IconFormat::Other => 0,
+            }
+    })
+}
+
+fn formats(flags: u16) -> Vec<IconFormat> {
+    [
+        (HAS_SUFFIX_PNG, IconFormat::Png),
+        (HAS_SUFFIX_SVG, IconFormat::Svg),
+        (HAS_SUFFIX_XPM, IconFormat::Xpm),
+    ]
+    .into_iter()
+    .filter(|(flag, _)| flags & flag != 0)
+    .map(|(_, format)| format)
+    .collect()
+}
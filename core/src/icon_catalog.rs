@@ -0,0 +1,473 @@
+use std::collections::{BTreeMap, HashSet};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::catalog_cache::{theme_stamps, CachedTheme, CatalogCache};
+use crate::gtk_icon_cache::{CacheEntry, GtkIconCache, CACHE_FILE_NAME};
+use crate::icon_theme::{DirectoryType, IconThemeDefinition, ThemeDirectory};
+use crate::search_paths::icon_search_paths;
+
+// Name of the pseudo-theme holding icons that lie directly in a search path, like
+// /usr/share/pixmaps. The spec's lookup falls back to them after every theme.
+pub const UNTHEMED: &str = "Unthemed";
+
+// Symbolic icons are monochrome versions of an icon that desktops recolor to match the
+// text, named like the full color icon plus this suffix
+pub const SYMBOLIC_SUFFIX: &str = "-symbolic";
+
+pub fn is_symbolic(icon_name: &str) -> bool {
+    icon_name.ends_with(SYMBOLIC_SUFFIX)
+}
+
+// The full color icon a symbolic icon stands for, None for names that aren't symbolic
+pub fn regular_name(icon_name: &str) -> Option<&str> {
+    icon_name.strip_suffix(SYMBOLIC_SUFFIX).filter(|name| !name.is_empty())
+}
+
+pub fn symbolic_name(icon_name: &str) -> String {
+    if is_symbolic(icon_name) {
+        icon_name.to_string()
+    } else {
+        format!("{}{}", icon_name, SYMBOLIC_SUFFIX)
+    }
+}
+
+// The icons of every installed theme, kept apart per theme in search path order
+#[derive(Debug, Clone)]
+pub struct IconCatalog {
+    themes: Vec<ThemeIcons>,
+    unthemed: ThemeIcons,
+}
+
+// The icons of one theme. A theme installed in several search paths (e.g. ~/.icons and
+// /usr/share/icons) is merged into one, the earlier path's variants come first.
+#[derive(Debug, Clone)]
+pub struct ThemeIcons {
+    pub directory_name: String,
+    pub name: String,
+    pub inherits: Vec<String>,
+    icons: BTreeMap<String, Vec<IconVariant>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct IconMetadata {
+    pub name: String,
+    pub variants: Vec<IconVariant>,
+    // Variants of the icon's symbolic version, empty if there is none or the icon is
+    // symbolic itself. The symbolic icon is also listed on its own.
+    pub symbolic: Vec<IconVariant>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IconVariant {
+    pub theme_name: String,
+    pub directory: ThemeDirectory,
+    pub path: PathBuf,
+    pub format: IconFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IconFormat {
+    Png,
+    Svg,
+    Xpm,
+    Other,
+}
+
+impl IconCatalog {
+    pub fn discover(theme_hint: Option<&str>) -> Result<Self> {
+        let (catalog, _) = Self::discover_with_cache(theme_hint, &CatalogCache::default())?;
+        Ok(catalog)
+    }
+
+    // Like `discover`, but reuses the scan of every theme whose index.theme and
+    // directories are unchanged since `cache` was made. Returns the updated cache.
+    pub fn discover_with_cache(theme_hint: Option<&str>, cache: &CatalogCache) -> Result<(Self, CatalogCache)> {
+        let mut theme_dirs = Vec::new();
+        let mut loose_files = Vec::new();
+        let mut seen_themes: HashSet<PathBuf> = HashSet::new();
+        for root in icon_search_paths() {
+            if !root.exists() {
+                continue;
+            }
+            let mut entries = std::fs::read_dir(&root)
+                .with_context(|| format!("Reading {}", root.display()))?
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<std::io::Result<Vec<_>>>()?;
+            entries.sort();
+            for path in entries {
+                if path.is_dir() {
+                    if seen_themes.insert(path.clone()) {
+                        theme_dirs.push(path);
+                    }
+                } else if theme_hint.is_none() {
+                    loose_files.push(path);
+                }
+            }
+        }
+
+        // Themes are loaded in parallel, collecting keeps the search path order so the
+        // variants are merged the same way every time
+        let started = Instant::now();
+        let loaded: Vec<Option<(IconThemeDefinition, CachedTheme, bool)>> = theme_dirs
+            .into_par_iter()
+            .map(|path| load_theme(path, theme_hint, cache))
+            .collect::<Result<_>>()?;
+
+        let mut themes: Vec<ThemeIcons> = Vec::new();
+        let mut scanned = Vec::new();
+        let mut rescanned = 0;
+        for (definition, theme, was_scanned) in loaded.into_iter().flatten() {
+            let index = match themes.iter().position(|t| t.directory_name == definition.directory_name) {
+                Some(index) => index,
+                None => {
+                    themes.push(ThemeIcons {
+                        directory_name: definition.directory_name,
+                        name: definition.name,
+                        inherits: definition.inherits,
+                        icons: BTreeMap::new(),
+                    });
+                    themes.len() - 1
+                }
+            };
+            for (name, variants) in &theme.icons {
+                themes[index].icons.entry(name.clone()).or_default().extend(variants.iter().cloned());
+            }
+            if was_scanned {
+                rescanned += 1;
+            }
+            scanned.push(theme);
+        }
+
+        log::info!(
+            "Scanned {} of {} themes in {:?}, the rest came from the cache",
+            rescanned,
+            scanned.len(),
+            started.elapsed()
+        );
+        let unthemed = unthemed_icons(loose_files);
+        Ok((Self { themes, unthemed }, CatalogCache::from_themes(scanned)))
+    }
+
+    pub fn themes(&self) -> &[ThemeIcons] {
+        &self.themes
+    }
+
+    pub fn unthemed(&self) -> &ThemeIcons {
+        &self.unthemed
+    }
+
+    // Looks a theme up by directory name or by its Name from index.theme
+    pub fn theme(&self, name: &str) -> Option<&ThemeIcons> {
+        self.themes
+            .iter()
+            .find(|theme| theme.directory_name == name)
+            .or_else(|| self.themes.iter().find(|theme| theme.name.eq_ignore_ascii_case(name)))
+    }
+
+    // The given themes followed by everything they inherit from and hicolor, in the order
+    // icon lookup searches them. Every theme appears once, unknown names are skipped.
+    pub fn inheritance_chain(&self, theme_names: &[String]) -> Vec<&ThemeIcons> {
+        let mut chain: Vec<&ThemeIcons> = Vec::new();
+        let mut pending: Vec<&str> = theme_names.iter().rev().map(String::as_str).collect();
+        let mut hicolor_added = false;
+        loop {
+            let Some(name) = pending.pop() else {
+                if hicolor_added {
+                    break;
+                }
+                hicolor_added = true;
+                pending.push("hicolor");
+                continue;
+            };
+            let Some(theme) = self.theme(name) else {
+                continue;
+            };
+            if chain.iter().any(|t| t.directory_name == theme.directory_name) {
+                continue;
+            }
+            chain.push(theme);
+            pending.extend(theme.inherits.iter().rev().map(String::as_str));
+        }
+        chain
+    }
+
+    // Every icon of every theme and the unthemed ones. The variants of an icon are in
+    // theme search path order.
+    pub fn iter(&self) -> impl Iterator<Item = IconMetadata> + '_ {
+        merge_icons(self.themes.iter().chain([&self.unthemed]))
+    }
+
+    // The icons visible when looking up through `theme_names`, their parents and finally
+    // the unthemed icons. The variants of the theme an icon is found in first come first.
+    pub fn icons_for_themes(&self, theme_names: &[String]) -> impl Iterator<Item = IconMetadata> + '_ {
+        merge_icons(self.inheritance_chain(theme_names).into_iter().chain([&self.unthemed]))
+    }
+}
+
+impl ThemeIcons {
+    pub fn icon_names(&self) -> impl Iterator<Item = &str> {
+        self.icons.keys().map(String::as_str)
+    }
+
+    pub fn variants(&self, icon_name: &str) -> &[IconVariant] {
+        self.icons.get(icon_name).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn symbolic_variants(&self, icon_name: &str) -> &[IconVariant] {
+        if is_symbolic(icon_name) {
+            return &[];
+        }
+        self.variants(&symbolic_name(icon_name))
+    }
+
+    // The variants of an icon by nominal size and scale. Scalable directories are listed
+    // under their nominal Size.
+    pub fn variants_by_size(&self, icon_name: &str) -> BTreeMap<(u32, u32), Vec<&IconVariant>> {
+        let mut sizes: BTreeMap<(u32, u32), Vec<&IconVariant>> = BTreeMap::new();
+        for variant in self.variants(icon_name) {
+            let size = variant.directory.size.unwrap_or(0);
+            sizes.entry((size, variant.directory.scale)).or_default().push(variant);
+        }
+        sizes
+    }
+}
+
+fn merge_icons<'a>(themes: impl Iterator<Item = &'a ThemeIcons>) -> impl Iterator<Item = IconMetadata> {
+    let mut icons: BTreeMap<String, IconMetadata> = BTreeMap::new();
+    for theme in themes {
+        for (name, variants) in &theme.icons {
+            icons
+                .entry(name.clone())
+                .or_insert_with(|| IconMetadata {
+                    name: name.clone(),
+                    variants: Vec::new(),
+                    symbolic: Vec::new(),
+                })
+                .variants
+                .extend(variants.iter().cloned());
+        }
+    }
+    let pairs: Vec<(String, Vec<IconVariant>)> = icons
+        .iter()
+        .filter_map(|(name, icon)| {
+            let regular = regular_name(name).filter(|regular| icons.contains_key(*regular))?;
+            Some((regular.to_string(), icon.variants.clone()))
+        })
+        .collect();
+    for (regular, variants) in pairs {
+        if let Some(icon) = icons.get_mut(&regular) {
+            icon.symbolic = variants;
+        }
+    }
+    icons.into_values()
+}
+
+// The icons of the theme in `path`, from the cache if it is still fresh. The flag tells
+// whether the theme had to be scanned. None if it isn't a theme or doesn't match the hint.
+fn load_theme(
+    path: PathBuf,
+    theme_hint: Option<&str>,
+    cache: &CatalogCache,
+) -> Result<Option<(IconThemeDefinition, CachedTheme, bool)>> {
+    let theme = match IconThemeDefinition::load_from_directory(&path) {
+        Ok(theme) => theme,
+        Err(err) => {
+            log::debug!("Skipping theme in {}: {}", path.display(), err);
+            return Ok(None);
+        }
+    };
+    if let Some(filter) = theme_hint {
+        let theme_name_lower = theme.name.to_lowercase();
+        if theme_name_lower != filter.to_lowercase()
+            && theme.directory_name.to_lowercase() != filter.to_lowercase()
+        {
+            return Ok(None);
+        }
+    }
+
+    let stamps = theme_stamps(&theme);
+    if let Some(cached) = cache.fresh_theme(&path, &stamps) {
+        return Ok(Some((theme, cached.clone(), false)));
+    }
+    let started = Instant::now();
+    let (icons, source) = match read_gtk_cache(&theme, &stamps) {
+        Some(icons) => (icons, CACHE_FILE_NAME),
+        None => (scan_theme(&theme), "the directories"),
+    };
+    log::info!(
+        "Read theme {} ({} icons) from {} in {:?}",
+        theme.directory_name,
+        icons.len(),
+        source,
+        started.elapsed()
+    );
+    Ok(Some((
+        theme,
+        CachedTheme {
+            root: path,
+            stamps,
+            icons,
+        },
+        true,
+    )))
+}
+
+// Icons lying directly in a search path. Earlier search paths come first, like in lookup.
+fn unthemed_icons(files: Vec<PathBuf>) -> ThemeIcons {
+    let directory = ThemeDirectory {
+        key: String::new(),
+        context: UNTHEMED.to_string(),
+        dir_type: DirectoryType::Threshold,
+        size: None,
+        min_size: None,
+        max_size: None,
+        threshold: None,
+        scale: 1,
+    };
+    let mut icons: BTreeMap<String, Vec<IconVariant>> = BTreeMap::new();
+    for path in files {
+        let format = IconFormat::from_path(&path);
+        if format == IconFormat::Other {
+            continue;
+        }
+        let Some(stem) = path.file_stem().and_then(OsStr::to_str).filter(|stem| !stem.is_empty()) else {
+            continue;
+        };
+        icons.entry(stem.to_string()).or_default().push(IconVariant {
+            theme_name: UNTHEMED.to_string(),
+            directory: directory.clone(),
+            path,
+            format,
+        });
+    }
+    ThemeIcons {
+        directory_name: UNTHEMED.to_string(),
+        name: UNTHEMED.to_string(),
+        inherits: Vec::new(),
+        icons,
+    }
+}
+
+// The theme's icons from its icon-theme.cache, if it has one that is at least as new as
+// index.theme and every directory. Directories the index doesn't list are left out, like
+// when walking.
+fn read_gtk_cache(
+    theme: &IconThemeDefinition,
+    stamps: &BTreeMap<PathBuf, Option<SystemTime>>,
+) -> Option<BTreeMap<String, Vec<IconVariant>>> {
+    let cache_path = theme.root_path.join(CACHE_FILE_NAME);
+    let cache_modified = std::fs::metadata(&cache_path).and_then(|m| m.modified()).ok()?;
+    if stamps.values().flatten().any(|modified| *modified > cache_modified) {
+        log::debug!("Ignoring outdated {}", cache_path.display());
+        return None;
+    }
+    let entries = match GtkIconCache::load(&cache_path).and_then(|cache| cache.entries()) {
+        Ok(entries) => entries,
+        Err(e) => {
+            log::warn!("{:?}", e);
+            return None;
+        }
+    };
+
+    let mut by_directory: BTreeMap<&str, Vec<&CacheEntry>> = BTreeMap::new();
+    for entry in &entries {
+        by_directory.entry(entry.directory.as_str()).or_default().push(entry);
+    }
+    let mut icons: BTreeMap<String, Vec<IconVariant>> = BTreeMap::new();
+    for directory in &theme.directories {
+        let Some(dir_entries) = by_directory.get_mut(directory.key.as_str()) else {
+            continue;
+        };
+        dir_entries.sort_by(|a, b| a.name.cmp(&b.name));
+        for entry in dir_entries.iter() {
+            for format in &entry.formats {
+                let file_name = format!("{}.{}", entry.name, format.extension());
+                icons.entry(entry.name.clone()).or_default().push(IconVariant {
+                    theme_name: theme.name.clone(),
+                    directory: directory.clone(),
+                    path: theme.root_path.join(&directory.key).join(file_name),
+                    format: *format,
+                });
+            }
+        }
+    }
+    Some(icons)
+}
+
+fn scan_theme(theme: &IconThemeDefinition) -> BTreeMap<String, Vec<IconVariant>> {
+    let per_directory: Vec<Vec<(String, IconVariant)>> = theme
+        .directories
+        .par_iter()
+        .map(|directory| scan_directory(theme, directory))
+        .collect();
+
+    let mut icons: BTreeMap<String, Vec<IconVariant>> = BTreeMap::new();
+    for (name, variant) in per_directory.into_iter().flatten() {
+        icons.entry(name).or_default().push(variant);
+    }
+    icons
+}
+
+fn scan_directory(theme: &IconThemeDefinition, directory: &ThemeDirectory) -> Vec<(String, IconVariant)> {
+    let dir_path = theme.root_path.join(&directory.key);
+    if !dir_path.exists() {
+        return Vec::new();
+    }
+    let mut found = Vec::new();
+    for entry in WalkDir::new(&dir_path)
+        .follow_links(true)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|res| res.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let path = entry.into_path();
+        let Some(stem) = path.file_stem().and_then(OsStr::to_str) else {
+            continue;
+        };
+        if stem.is_empty() {
+            continue;
+        }
+        let format = IconFormat::from_path(&path);
+        if format == IconFormat::Other {
+            continue;
+        }
+        found.push((
+            stem.to_string(),
+            IconVariant {
+                theme_name: theme.name.clone(),
+                directory: directory.clone(),
+                path: path.clone(),
+                format,
+            },
+        ));
+    }
+    found
+}
+
+impl IconFormat {
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(OsStr::to_str).map(|s| s.to_lowercase()) {
+            Some(ref e) if e == "png" => IconFormat::Png,
+            Some(ref e) if e == "svg" => IconFormat::Svg,
+            Some(ref e) if e == "xpm" => IconFormat::Xpm,
+            _ => IconFormat::Other,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            IconFormat::Png => "png",
+            IconFormat::Svg => "svg",
+            IconFormat::Xpm => "xpm",
+            IconFormat::Other => "",
+        }
+    }
+}
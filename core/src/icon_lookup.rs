@@ -0,0 +1,225 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::icon_catalog::{is_symbolic, regular_name, symbolic_name};
+use crate::icon_theme::{DirectoryType, IconThemeDefinition, ThemeDirectory};
+
+const ICON_EXTENSIONS: [&str; 3] = ["png", "svg", "xpm"];
+
+// Implements the lookup algorithm from the freedesktop Icon Theme Specification
+// (FindIcon / FindIconHelper / LookupIcon / LookupFallbackIcon).
+#[derive(Debug, Default)]
+pub struct IconLookup {
+    base_dirs: Vec<PathBuf>,
+    themes: HashMap<String, Option<ResolvedTheme>>,
+}
+
+#[derive(Debug, Clone)]
+struct ResolvedTheme {
+    definition: IconThemeDefinition,
+    // Every base dir that has a folder with this theme's directory name. Icons
+    // of a theme may be spread over several of them (e.g. ~/.icons and /usr/share/icons).
+    roots: Vec<PathBuf>,
+}
+
+impl IconLookup {
+    pub fn new(base_dirs: Vec<PathBuf>) -> Self {
+        Self {
+            base_dirs,
+            themes: HashMap::new(),
+        }
+    }
+
+    // Falls back to the more generic names of `fallback_names` like GTK does: every theme
+    // is asked for all of them before its parents are.
+    pub fn find_icon(
+        &mut self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        theme_name: &str,
+    ) -> Option<PathBuf> {
+        let icon_names = fallback_names(icon_name);
+        let mut visited = HashSet::new();
+        if let Some(path) = self.find_icon_helper(&icon_names, size, scale, theme_name, &mut visited) {
+            return Some(path);
+        }
+        if let Some(path) = self.find_icon_helper(&icon_names, size, scale, "hicolor", &mut visited) {
+            return Some(path);
+        }
+        icon_names.iter().find_map(|name| self.lookup_fallback_icon(name))
+    }
+
+    fn find_icon_helper(
+        &mut self,
+        icon_names: &[String],
+        size: u32,
+        scale: u32,
+        theme_name: &str,
+        visited: &mut HashSet<String>,
+    ) -> Option<PathBuf> {
+        // Inherits chains may contain cycles or diamonds; every theme is searched at most once
+        if !visited.insert(theme_name.to_string()) {
+            return None;
+        }
+        let theme = self.resolve_theme(theme_name)?;
+        if let Some(path) = icon_names.iter().find_map(|name| lookup_icon(&theme, name, size, scale)) {
+            return Some(path);
+        }
+        for parent in &theme.definition.inherits {
+            if let Some(path) = self.find_icon_helper(icon_names, size, scale, parent, visited) {
+                return Some(path);
+            }
+        }
+        None
+    }
+
+    fn lookup_fallback_icon(&self, icon_name: &str) -> Option<PathBuf> {
+        for base_dir in &self.base_dirs {
+            for ext in ICON_EXTENSIONS {
+                let path = base_dir.join(format!("{}.{}", icon_name, ext));
+                if path.is_file() {
+                    return Some(path);
+                }
+            }
+        }
+        None
+    }
+
+    fn resolve_theme(&mut self, theme_name: &str) -> Option<ResolvedTheme> {
+        if let Some(cached) = self.themes.get(theme_name) {
+            return cached.clone();
+        }
+
+        let roots: Vec<PathBuf> = self
+            .base_dirs
+            .iter()
+            .map(|base| base.join(theme_name))
+            .filter(|path| path.is_dir())
+            .collect();
+
+        // The first root with an index.theme defines the theme
+        let resolved = roots.iter().find_map(|root| {
+            match IconThemeDefinition::load_from_directory(root) {
+                Ok(definition) => Some(ResolvedTheme {
+                    definition,
+                    roots: roots.clone(),
+                }),
+                Err(err) => {
+                    log::debug!("No theme definition in {}: {}", root.display(), err);
+                    None
+                }
+            }
+        });
+
+        self.themes.insert(theme_name.to_string(), resolved.clone());
+        resolved
+    }
+}
+
+// The names looked up for `icon_name`, most specific first. Following the Icon Naming
+// Specification every dash drops a detail: network-wireless-signal-good falls back to
+// network-wireless-signal, network-wireless and network. Symbolic names try the symbolic
+// versions of all of them before the full color ones.
+pub fn fallback_names(icon_name: &str) -> Vec<String> {
+    let base = regular_name(icon_name).unwrap_or(icon_name);
+    let mut prefixes = vec![base];
+    let mut rest = base;
+    while let Some((prefix, _)) = rest.rsplit_once('-') {
+        if prefix.is_empty() {
+            break;
+        }
+        prefixes.push(prefix);
+        rest = prefix;
+    }
+    let mut names = Vec::new();
+    if is_symbolic(icon_name) {
+        names.extend(prefixes.iter().map(|prefix| symbolic_name(prefix)));
+    }
+    names.extend(prefixes.iter().map(|prefix| prefix.to_string()));
+    names
+}
+
+fn lookup_icon(theme: &ResolvedTheme, icon_name: &str, size: u32, scale: u32) -> Option<PathBuf> {
+    for directory in &theme.definition.directories {
+        if !directory_matches_size(directory, size, scale) {
+            continue;
+        }
+        for root in &theme.roots {
+            if let Some(path) = find_in_directory(&root.join(&directory.key), icon_name) {
+                return Some(path);
+            }
+        }
+    }
+
+    let mut closest: Option<(u32, PathBuf)> = None;
+    for directory in &theme.definition.directories {
+        let distance = directory_size_distance(directory, size, scale);
+        if closest.as_ref().is_some_and(|(min, _)| distance >= *min) {
+            continue;
+        }
+        for root in &theme.roots {
+            if let Some(path) = find_in_directory(&root.join(&directory.key), icon_name) {
+                closest = Some((distance, path));
+                break;
+            }
+        }
+    }
+    closest.map(|(_, path)| path)
+}
+
+fn find_in_directory(dir: &Path, icon_name: &str) -> Option<PathBuf> {
+    ICON_EXTENSIONS
+        .iter()
+        .map(|ext| dir.join(format!("{}.{}", icon_name, ext)))
+        .find(|path| path.is_file())
+}
+
+fn directory_matches_size(directory: &ThemeDirectory, size: u32, scale: u32) -> bool {
+    if directory.scale != scale {
+        return false;
+    }
+    let (nominal, min, max, threshold) = size_bounds(directory);
+    match directory.dir_type {
+        DirectoryType::Fixed => nominal == size,
+        DirectoryType::Scaled => min <= size && size <= max,
+        DirectoryType::Threshold => {
+            nominal.saturating_sub(threshold) <= size && size <= nominal + threshold
+        }
+    }
+}
+
+// Distances are compared in device pixels so that e.g. 16@2 is a perfect match for 32@1
+fn directory_size_distance(directory: &ThemeDirectory, size: u32, scale: u32) -> u32 {
+    let (nominal, min, max, threshold) = size_bounds(directory);
+    let dir_scale = directory.scale;
+    let pixels = size * scale;
+    match directory.dir_type {
+        DirectoryType::Fixed => (nominal * dir_scale).abs_diff(pixels),
+        DirectoryType::Scaled => distance_outside(pixels, min * dir_scale, max * dir_scale),
+        DirectoryType::Threshold => distance_outside(
+            pixels,
+            nominal.saturating_sub(threshold) * dir_scale,
+            (nominal + threshold) * dir_scale,
+        ),
+    }
+}
+
+fn distance_outside(size: u32, min: u32, max: u32) -> u32 {
+    if size < min {
+        min - size
+    } else {
+        size.saturating_sub(max)
+    }
+}
+
+// MinSize and MaxSize default to Size, Threshold defaults to 2
+fn size_bounds(directory: &ThemeDirectory) -> (u32, u32, u32, u32) {
+    let nominal = directory.size.unwrap_or(0);
+    (
+        nominal,
+        directory.min_size.unwrap_or(nominal),
+        directory.max_size.unwrap_or(nominal),
+        directory.threshold.unwrap_or(2),
+    )
+}
@@ -0,0 +1,114 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::index_theme::{IndexTheme, ICON_THEME_GROUP};
+
+#[derive(Debug, Clone)]
+pub struct IconThemeDefinition {
+    pub directory_name: String,
+    pub name: String,
+    pub comment: Option<String>,
+    pub example: Option<String>,
+    pub hidden: bool,
+    pub inherits: Vec<String>,
+    pub directories: Vec<ThemeDirectory>,
+    pub root_path: PathBuf,
+    // The whole file, with the keys that have no field above, like Name[de] or KDE's
+    // FollowsColorScheme
+    pub index: IndexTheme,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThemeDirectory {
+    pub key: String,
+    pub context: String,
+    pub dir_type: DirectoryType,
+    pub size: Option<u32>,
+    pub min_size: Option<u32>,
+    pub max_size: Option<u32>,
+    pub threshold: Option<u32>,
+    pub scale: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DirectoryType {
+    Fixed,
+    Scaled,
+    Threshold,
+}
+
+impl IconThemeDefinition {
+    pub fn load_from_directory(theme_dir: &Path) -> Result<Self> {
+        let index_path = theme_dir.join("index.theme");
+        if !index_path.exists() {
+            anyhow::bail!("Missing index.theme in {}", theme_dir.to_string_lossy());
+        }
+        let index = IndexTheme::load(&index_path)?;
+
+        let dir_defs = index
+            .get_list(ICON_THEME_GROUP, "Directories")
+            .into_iter()
+            .chain(index.get_list(ICON_THEME_GROUP, "ScaledDirectories"))
+            .map(|dir_name| parse_directory_section(&index, &dir_name))
+            .collect::<Vec<_>>();
+
+        let directory_name = theme_dir
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_else(|| String::from("<unknown>"));
+        Ok(Self {
+            name: index
+                .get(ICON_THEME_GROUP, "Name")
+                .map(str::to_string)
+                .unwrap_or_else(|| directory_name.clone()),
+            directory_name,
+            comment: index.get(ICON_THEME_GROUP, "Comment").map(str::to_string),
+            example: index.get(ICON_THEME_GROUP, "Example").map(str::to_string),
+            hidden: index.get(ICON_THEME_GROUP, "Hidden") == Some("true"),
+            inherits: index.get_list(ICON_THEME_GROUP, "Inherits"),
+            directories: dir_defs,
+            root_path: theme_dir.to_path_buf(),
+            index,
+        })
+    }
+
+    // Name[xx] for `locale` (a value like $LANG), or the plain Name
+    pub fn localized_name(&self, locale: &str) -> &str {
+        self.index
+            .get_localized(ICON_THEME_GROUP, "Name", locale)
+            .unwrap_or(&self.name)
+    }
+
+    pub fn localized_comment(&self, locale: &str) -> Option<&str> {
+        self.index.get_localized(ICON_THEME_GROUP, "Comment", locale)
+    }
+}
+
+fn parse_directory_section(index: &IndexTheme, dir_name: &str) -> ThemeDirectory {
+    let number = |key: &str| index.get(dir_name, key).and_then(|v| v.parse::<u32>().ok());
+    ThemeDirectory {
+        key: dir_name.to_string(),
+        context: index.get(dir_name, "Context").unwrap_or("Generic").to_string(),
+        dir_type: index
+            .get(dir_name, "Type")
+            .map(DirectoryType::from_str)
+            .unwrap_or(DirectoryType::Threshold),
+        size: number("Size"),
+        min_size: number("MinSize"),
+        max_size: number("MaxSize"),
+        threshold: number("Threshold"),
+        scale: number("Scale").filter(|scale| *scale > 0).unwrap_or(1),
+    }
+}
+
+impl DirectoryType {
+    fn from_str(input: &str) -> Self {
+        match input {
+            "Fixed" => DirectoryType::Fixed,
+            "Scaled" => DirectoryType::Scaled,
+            _ => DirectoryType::Threshold,
+        }
+    }
+}
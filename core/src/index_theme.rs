@@ -0,0 +1,237 @@
+use std::fmt;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+pub const ICON_THEME_GROUP: &str = "Icon Theme";
+
+// An index.theme file as a list of groups and their lines. Every key is kept, including
+// localized ones like Name[de] and extensions like KDE's FollowsColorScheme, together with
+// comments and blank lines, so a parsed file is written back exactly as it was read.
+// Keys are case sensitive, as the desktop entry format says. Lines that aren't a group,
+// key or comment are kept as they are, like other implementations ignore them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IndexTheme {
+    // Lines before the first group
+    header: Vec<Line>,
+    groups: Vec<Group>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Group {
+    pub name: String,
+    lines: Vec<Line>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Line {
+    // `raw` is the line as read, None once the value was changed
+    Entry { key: String, value: String, raw: Option<String> },
+    Other(String),
+}
+
+impl IndexTheme {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(Self::parse(&content))
+    }
+
+    pub fn parse(content: &str) -> Self {
+        let mut index = Self::default();
+        for line in content.lines() {
+            let trimmed = line.trim();
+            if trimmed.starts_with('[') && trimmed.ends_with(']') {
+                index.groups.push(Group {
+                    name: trimmed[1..trimmed.len() - 1].to_string(),
+                    lines: Vec::new(),
+                });
+                continue;
+            }
+            let parsed = match line.split_once('=') {
+                Some((key, value)) if !trimmed.starts_with('#') => Line::Entry {
+                    key: key.trim().to_string(),
+                    value: value.trim().to_string(),
+                    raw: Some(line.to_string()),
+                },
+                _ => Line::Other(line.to_string()),
+            };
+            match index.groups.last_mut() {
+                Some(group) => group.lines.push(parsed),
+                None => index.header.push(parsed),
+            }
+        }
+        index
+    }
+
+    pub fn groups(&self) -> impl Iterator<Item = &Group> {
+        self.groups.iter()
+    }
+
+    pub fn group(&self, name: &str) -> Option<&Group> {
+        self.groups.iter().find(|group| group.name == name)
+    }
+
+    pub fn get(&self, group: &str, key: &str) -> Option<&str> {
+        self.group(group)?.get(key)
+    }
+
+    // The value for `locale` (like de_DE.UTF-8@euro), looked up the way the desktop entry
+    // spec describes: lang_COUNTRY@MODIFIER, lang_COUNTRY, lang@MODIFIER, lang and
+    // finally the unlocalized key
+    pub fn get_localized(&self, group: &str, key: &str, locale: &str) -> Option<&str> {
+        let group = self.group(group)?;
+        let locale = locale.split('.').next().unwrap_or_default();
+        let (locale, modifier) = match locale.split_once('@') {
+            Some((locale, modifier)) => (locale, Some(modifier)),
+            None => (locale, None),
+        };
+        let (lang, country) = match locale.split_once('_') {
+            Some((lang, country)) => (lang, Some(country)),
+            None => (locale, None),
+        };
+        let mut candidates = Vec::new();
+        if let (Some(country), Some(modifier)) = (country, modifier) {
+            candidates.push(format!("{}_{}@{}", lang, country, modifier));
+        }
+        if let Some(country) = country {
+            candidates.push(format!("{}_{}", lang, country));
+        }
+        if let Some(modifier) = modifier {
+            candidates.push(format!("{}@{}", lang, modifier));
+        }
+        if !lang.is_empty() {
+            candidates.push(lang.to_string());
+        }
+        candidates
+            .iter()
+            .find_map(|candidate| group.get(&format!("{}[{}]", key, candidate)))
+            .or_else(|| group.get(key))
+    }
+
+    // Comma separated values, like Directories or Inherits
+    pub fn get_list(&self, group: &str, key: &str) -> Vec<String> {
+        self.get(group, key)
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    // Changes the value in place, or adds the key after the group's last key. A missing
+    // group is added at the end.
+    pub fn set(&mut self, group: &str, key: &str, value: &str) {
+        self.group_mut(group).set(key, value);
+    }
+
+    pub fn remove(&mut self, group: &str, key: &str) {
+        if let Some(group) = self.groups.iter_mut().find(|g| g.name == group) {
+            group.lines.retain(|line| !matches!(line, Line::Entry { key: k, .. } if k == key));
+        }
+    }
+
+    pub fn remove_group(&mut self, name: &str) {
+        let Some(index) = self.groups.iter().position(|group| group.name == name) else {
+            return;
+        };
+        self.groups.remove(index);
+        // The last group doesn't end in the blank line that separated it from the next one
+        if index == self.groups.len() {
+            if let Some(previous) = self.groups.last_mut() {
+                while matches!(previous.lines.last(), Some(Line::Other(line)) if line.trim().is_empty()) {
+                    previous.lines.pop();
+                }
+            }
+        }
+    }
+
+    fn group_mut(&mut self, name: &str) -> &mut Group {
+        if let Some(index) = self.groups.iter().position(|group| group.name == name) {
+            return &mut self.groups[index];
+        }
+        if let Some(last) = self.groups.last_mut() {
+            if !matches!(last.lines.last(), Some(Line::Other(line)) if line.trim().is_empty()) {
+                last.lines.push(Line::Other(String::new()));
+            }
+        }
+        self.groups.push(Group {
+            name: name.to_string(),
+            lines: Vec::new(),
+        });
+        self.groups.last_mut().unwrap()
+    }
+}
+
+impl Group {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.lines.iter().find_map(|line| match line {
+            Line::Entry { key: k, value, .. } if k == key => Some(value.as_str()),
+            _ => None,
+        })
+    }
+
+    // Every key and value in file order
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.lines.iter().filter_map(|line| match line {
+            Line::Entry { key, value, .. } => Some((key.as_str(), value.as_str())),
+            Line::Other(_) => None,
+        })
+    }
+
+    fn set(&mut self, key: &str, value: &str) {
+        for line in &mut self.lines {
+            if let Line::Entry { key: k, value: v, raw } = line {
+                if k == key {
+                    if v != value {
+                        *v = value.to_string();
+                        *raw = None;
+                    }
+                    return;
+                }
+            }
+        }
+        let position = self
+            .lines
+            .iter()
+            .rposition(|line| matches!(line, Line::Entry { .. }))
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        self.lines.insert(
+            position,
+            Line::Entry {
+                key: key.to_string(),
+                value: value.to_string(),
+                raw: None,
+            },
+        );
+    }
+}
+
+impl fmt::Display for IndexTheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.header {
+            writeln!(f, "{}", line)?;
+        }
+        for group in &self.groups {
+            writeln!(f, "[{}]", group.name)?;
+            for line in &group.lines {
+                writeln!(f, "{}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Line::Entry { raw: Some(raw), .. } => write!(f, "{}", raw),
+            Line::Entry { key, value, raw: None } => write!(f, "{}={}", key, value),
+            Line::Other(line) => write!(f, "{}", line),
+        }
+    }
+}
@@ -0,0 +1,18 @@
+// Everything icon-packer does that doesn't need Qt: reading icon themes, projects and
+// generating themes. Shared by the GUI and the command line interface.
+pub mod assets;
+pub mod catalog_cache;
+pub mod catalog_watcher;
+pub mod gtk_icon_cache;
+pub mod icon_catalog;
+pub mod icon_lookup;
+pub mod icon_theme;
+pub mod index_theme;
+pub mod live_generation;
+pub mod manifest;
+pub mod project;
+pub mod rasterizer;
+pub mod search_paths;
+pub mod staging;
+pub mod theme_generator;
+pub mod theme_layout;
@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::assets;
+use crate::project::{IconProject, PROJECT_FILE_NAME};
+use crate::theme_generator::{GenerationCancelled, GenerationReport, ThemePackGenerator};
+
+// How long the project has to stay unchanged before a run starts
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+pub struct LiveJob {
+    pub project: IconProject,
+    pub output_path: PathBuf,
+}
+
+pub enum LiveEvent {
+    Started,
+    Progress { done: usize, total: usize },
+    // Original -> stored copy, relative to the project directory
+    AssetsImported { project_dir: PathBuf, imported: BTreeMap<PathBuf, PathBuf> },
+    Finished(GenerationReport),
+    Exported(PathBuf),
+    Failed(String),
+    Cancelled,
+}
+
+enum Task {
+    Live(u64, LiveJob),
+    // Generates the project under another name and place, without saving it there
+    Export { job: LiveJob, theme_name: String },
+}
+
+// Runs live generation on a background thread. Bursts of edits are merged into one run
+// and a job submitted while another one is running cancels it. Exports run on the same
+// thread, in order with the live runs, and are never cancelled.
+pub struct LiveGenerationWorker {
+    sender: Sender<Task>,
+    latest: Arc<AtomicU64>,
+}
+
+impl LiveGenerationWorker {
+    pub fn new(notify: impl Fn(LiveEvent) + Send + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let latest = Arc::new(AtomicU64::new(0));
+        let worker_latest = Arc::clone(&latest);
+        thread::spawn(move || run(receiver, worker_latest, notify));
+        Self { sender, latest }
+    }
+
+    pub fn submit(&self, job: LiveJob) {
+        let id = self.latest.fetch_add(1, Ordering::SeqCst) + 1;
+        if self.sender.send(Task::Live(id, job)).is_err() {
+            log::error!("Live generation worker has stopped");
+        }
+    }
+
+    pub fn export(&self, job: LiveJob, theme_name: String) {
+        if self.sender.send(Task::Export { job, theme_name }).is_err() {
+            log::error!("Live generation worker has stopped");
+        }
+    }
+}
+
+fn export(job: LiveJob, theme_name: String, notify: &impl Fn(LiveEvent)) {
+    notify(LiveEvent::Started);
+    let generator = ThemePackGenerator::from_project(&job.project, theme_name, job.output_path.clone());
+    let mut reported = None;
+    let result = generator.generate_with_progress(|done, total| {
+        let percent = (done * 100).checked_div(total).unwrap_or(100);
+        if reported != Some(percent) {
+            reported = Some(percent);
+            notify(LiveEvent::Progress { done, total });
+        }
+        true
+    });
+    match result {
+        Ok(_) => {
+            log::info!("Theme generated successfully to: {}", job.output_path.display());
+            notify(LiveEvent::Exported(job.output_path));
+        }
+        Err(e) => {
+            log::error!("Failed to generate theme: {:?}", e);
+            notify(LiveEvent::Failed(format!("{:#}", e)));
+        }
+    }
+}
+
+fn run(receiver: Receiver<Task>, latest: Arc<AtomicU64>, notify: impl Fn(LiveEvent)) {
+    // Jobs built before the GUI heard of an import still carry the original paths
+    let mut imported = BTreeMap::new();
+    while let Ok(task) = receiver.recv() {
+        let mut next = match task {
+            Task::Live(id, job) => (id, job),
+            Task::Export { job, theme_name } => {
+                export(job, theme_name, &notify);
+                continue;
+            }
+        };
+        // Keep taking newer jobs until edits settle down
+        loop {
+            match receiver.recv_timeout(DEBOUNCE) {
+                Ok(Task::Live(id, job)) => next = (id, job),
+                Ok(Task::Export { job, theme_name }) => export(job, theme_name, &notify),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+
+        let (id, mut job) = next;
+        if latest.load(Ordering::SeqCst) != id {
+            continue;
+        }
+        notify(LiveEvent::Started);
+        // Imports happen here rather than on the GUI thread, in order with the runs that
+        // read and clean up the asset folder
+        let import = if job.project.vendor_assets {
+            assets::import_assets(&mut job.project, &job.output_path, &mut imported)
+        } else {
+            Ok(0)
+        };
+        if matches!(import, Ok(changed) if changed > 0) {
+            notify(LiveEvent::AssetsImported {
+                project_dir: job.output_path.clone(),
+                imported: imported.clone(),
+            });
+        }
+        // Only report whole percent steps, every event is a round trip to the GUI thread
+        let mut reported = None;
+        let result = import.and_then(|_| {
+            let generator = ThemePackGenerator::from_project(&job.project, job.project.display_theme_name(), job.output_path.clone());
+            generator.generate_with_progress(|done, total| {
+                let percent = (done * 100).checked_div(total).unwrap_or(100);
+                if reported != Some(percent) {
+                    reported = Some(percent);
+                    notify(LiveEvent::Progress { done, total });
+                }
+                latest.load(Ordering::SeqCst) == id
+            })
+        });
+        if let Err(ref e) = result {
+            if e.is::<GenerationCancelled>() {
+                log::debug!("Live generation superseded by a newer change");
+                notify(LiveEvent::Cancelled);
+                continue;
+            }
+        }
+
+        // The project is saved even when generation failed, it is the only copy of the edits
+        let metadata_path = job.output_path.join(PROJECT_FILE_NAME);
+        if let Err(e) = job.project.save(&metadata_path) {
+            log::warn!("Failed to auto-save project metadata: {:?}", e);
+        } else {
+            log::debug!("Project metadata auto-saved to: {:?}", metadata_path);
+            // A failed run may have left paths pointing outside the store, keep everything
+            if result.is_ok() {
+                if let Err(e) = assets::collect_garbage(&job.project, &job.output_path) {
+                    log::warn!("Failed to remove unused assets: {:?}", e);
+                }
+            }
+        }
+        match result {
+            Ok(report) => {
+                log::debug!("Theme generated live to: {}", job.output_path.display());
+                notify(LiveEvent::Finished(report));
+            }
+            Err(e) => {
+                log::error!("Failed to generate theme live: {:?}", e);
+                notify(LiveEvent::Failed(format!("{:#}", e)));
+            }
+        }
+    }
+}
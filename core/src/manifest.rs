@@ -0,0 +1,139 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::theme_generator::FileAction;
+
+pub const MANIFEST_FILE_NAME: &str = ".icon-packer-manifest.json";
+
+// Every file the generator created in an output directory, keyed by path relative to it.
+// Cleanup only ever touches files listed here.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GenerationManifest {
+    pub files: BTreeMap<PathBuf, FileState>,
+    // What each generated file was made from, so unchanged outputs can be skipped
+    #[serde(default)]
+    pub sources: BTreeMap<PathBuf, SourceRecord>,
+    // Directories listed in index.theme by the run, the only ones a later run may drop
+    #[serde(default)]
+    pub directories: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceRecord {
+    pub source: PathBuf,
+    pub action: FileAction,
+    pub source_stamp: Option<FileStamp>, // None when the source couldn't be read, e.g. dangling links
+    pub source_sha256: Option<String>,
+    pub output_stamp: Option<FileStamp>, // None for symlinks, which are checked by target
+}
+
+// Cheap change detection; content hashes are only compared when the stamp differs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStamp {
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileState {
+    Sha256(String),
+    Link(PathBuf),
+}
+
+impl GenerationManifest {
+    pub fn load(output_dir: &Path) -> Result<Option<Self>> {
+        let path = output_dir.join(MANIFEST_FILE_NAME);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let manifest = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        Ok(Some(manifest))
+    }
+
+    pub fn save(&self, output_dir: &Path) -> Result<()> {
+        let path = output_dir.join(MANIFEST_FILE_NAME);
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(())
+    }
+
+    pub fn record(&mut self, output_dir: &Path, relative: PathBuf) -> Result<()> {
+        let state = FileState::read(&output_dir.join(&relative))?;
+        self.files.insert(relative, state);
+        Ok(())
+    }
+
+    // Manifests of older versions don't list directories, there every directory a
+    // generated file is in counts
+    pub fn generated_directories(&self) -> BTreeSet<String> {
+        let mut directories = self.directories.clone();
+        for path in self.files.keys() {
+            if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+                directories.insert(parent.to_string_lossy().to_string());
+            }
+        }
+        directories
+    }
+
+    // Takes over the entries of a file that was left untouched from an earlier manifest
+    pub fn carry_over(&mut self, previous: &GenerationManifest, relative: &Path) {
+        if let Some(state) = previous.files.get(relative) {
+            self.files.insert(relative.to_path_buf(), state.clone());
+        }
+        if let Some(record) = previous.sources.get(relative) {
+            self.sources.insert(relative.to_path_buf(), record.clone());
+        }
+    }
+}
+
+impl FileStamp {
+    pub fn read(path: &Path) -> Result<Self> {
+        let metadata = fs::metadata(path)
+            .with_context(|| format!("Failed to stat {}", path.display()))?;
+        Ok(Self {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+}
+
+impl FileState {
+    // Symlinks are identified by their target, everything else by content
+    pub fn read(path: &Path) -> Result<Self> {
+        let metadata = fs::symlink_metadata(path)
+            .with_context(|| format!("Failed to stat {}", path.display()))?;
+        if metadata.file_type().is_symlink() {
+            let target = fs::read_link(path)
+                .with_context(|| format!("Failed to read link {}", path.display()))?;
+            return Ok(FileState::Link(target));
+        }
+        Ok(FileState::Sha256(hash_file(path)?))
+    }
+}
+
+pub fn hash_file(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
@@ -0,0 +1,252 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+
+use crate::theme_layout::ThemeLayout;
+
+pub const PROJECT_FILE_NAME: &str = ".icon-packer-project.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IconProject {
+    pub name: String,
+    pub theme_name: String,
+    #[serde(default)]
+    pub theme_comment: String,
+    pub output_path: Option<PathBuf>,
+    pub fallback_themes: Vec<String>, // Themes to inherit from
+    pub icon_replacements: BTreeMap<String, PathBuf>,
+    pub size_specific_replacements: BTreeMap<String, BTreeMap<u32, PathBuf>>, // icon_name -> size -> path
+    #[serde(default)]
+    pub icon_links: BTreeMap<String, bool>, // icon_name -> is_link (for base replacements)
+    #[serde(default)]
+    pub size_specific_links: BTreeMap<String, BTreeMap<u32, bool>>, // icon_name -> size -> is_link
+    #[serde(default)]
+    pub icon_categories: BTreeMap<String, String>, // icon_name -> category
+    #[serde(default)]
+    pub scaled_replacements: BTreeMap<String, BTreeMap<u32, BTreeMap<u32, PathBuf>>>, // icon_name -> scale -> size -> path
+    #[serde(default)]
+    pub scaled_links: BTreeMap<String, BTreeMap<u32, BTreeMap<u32, bool>>>, // icon_name -> scale -> size -> is_link
+    #[serde(default)]
+    pub icon_aliases: BTreeMap<String, String>, // alias -> icon it links to
+    #[serde(default)]
+    pub layout: ThemeLayout, // Sizes, contexts and directory naming of the generated theme
+    #[serde(default = "default_true")]
+    pub write_icon_cache: bool, // Write a GTK icon-theme.cache next to index.theme
+    #[serde(default)]
+    pub allow_external_links: bool, // Linked replacements point at their source instead of a copy in the theme
+    #[serde(default)]
+    pub vendor_assets: bool, // Import replacement files into the project's assets folder
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl IconProject {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            theme_name: String::new(),
+            theme_comment: String::new(),
+            output_path: None,
+            fallback_themes: vec!["hicolor".to_string()], // Default fallback
+            icon_replacements: BTreeMap::new(),
+            size_specific_replacements: BTreeMap::new(),
+            icon_links: BTreeMap::new(),
+            size_specific_links: BTreeMap::new(),
+            icon_categories: BTreeMap::new(),
+            scaled_replacements: BTreeMap::new(),
+            scaled_links: BTreeMap::new(),
+            icon_aliases: BTreeMap::new(),
+            layout: ThemeLayout::default(),
+            write_icon_cache: true,
+            allow_external_links: false,
+            vendor_assets: false,
+        }
+    }
+
+    pub fn save(&self, path: &PathBuf) -> anyhow::Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    pub fn load(path: &PathBuf) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let project: IconProject = serde_json::from_str(&content)?;
+        Ok(project)
+    }
+
+    // Name written to index.theme, the project name unless one was set explicitly
+    pub fn display_theme_name(&self) -> String {
+        if !self.theme_name.is_empty() {
+            self.theme_name.clone()
+        } else {
+            self.name.clone()
+        }
+    }
+
+    // Replacement paths are relative to the project directory once imported as assets
+    pub fn resolve_path(&self, path: &Path) -> PathBuf {
+        match self.output_path {
+            Some(ref dir) if path.is_relative() => dir.join(path),
+            _ => path.to_path_buf(),
+        }
+    }
+
+    pub fn set_replacement(&mut self, icon_name: &str, path: PathBuf) {
+        self.icon_replacements.insert(icon_name.to_string(), path);
+    }
+
+    // The remove methods return false when there is no such replacement
+    pub fn remove_replacement(&mut self, icon_name: &str) -> bool {
+        self.icon_links.remove(icon_name);
+        self.icon_categories.remove(icon_name);
+        self.icon_replacements.remove(icon_name).is_some()
+    }
+
+    pub fn set_size_replacement(&mut self, icon_name: &str, size: u32, path: PathBuf) {
+        self.size_specific_replacements
+            .entry(icon_name.to_string())
+            .or_default()
+            .insert(size, path);
+    }
+
+    pub fn remove_size_replacement(&mut self, icon_name: &str, size: u32) -> bool {
+        let mut removed = false;
+        if let Some(size_map) = self.size_specific_replacements.get_mut(icon_name) {
+            removed = size_map.remove(&size).is_some();
+            if size_map.is_empty() {
+                self.size_specific_replacements.remove(icon_name);
+            }
+        }
+        if let Some(link_map) = self.size_specific_links.get_mut(icon_name) {
+            link_map.remove(&size);
+            if link_map.is_empty() {
+                self.size_specific_links.remove(icon_name);
+            }
+        }
+        removed
+    }
+
+    pub fn set_scaled_replacement(&mut self, icon_name: &str, size: u32, scale: u32, path: PathBuf) {
+        self.scaled_replacements
+            .entry(icon_name.to_string())
+            .or_default()
+            .entry(scale)
+            .or_default()
+            .insert(size, path);
+    }
+
+    pub fn remove_scaled_replacement(&mut self, icon_name: &str, size: u32, scale: u32) -> bool {
+        let mut removed = false;
+        if let Some(scale_map) = self.scaled_replacements.get_mut(icon_name) {
+            if let Some(size_map) = scale_map.get_mut(&scale) {
+                removed = size_map.remove(&size).is_some();
+                if size_map.is_empty() {
+                    scale_map.remove(&scale);
+                }
+            }
+            if scale_map.is_empty() {
+                self.scaled_replacements.remove(icon_name);
+            }
+        }
+        if let Some(scale_map) = self.scaled_links.get_mut(icon_name) {
+            if let Some(link_map) = scale_map.get_mut(&scale) {
+                link_map.remove(&size);
+                if link_map.is_empty() {
+                    scale_map.remove(&scale);
+                }
+            }
+            if scale_map.is_empty() {
+                self.scaled_links.remove(icon_name);
+            }
+        }
+        removed
+    }
+
+    // The link setters return false when there is no such replacement to link
+    pub fn set_replacement_link(&mut self, icon_name: &str, is_link: bool) -> bool {
+        if !self.icon_replacements.contains_key(icon_name) {
+            return false;
+        }
+        self.icon_links.insert(icon_name.to_string(), is_link);
+        true
+    }
+
+    pub fn set_size_replacement_link(&mut self, icon_name: &str, size: u32, is_link: bool) -> bool {
+        let exists = self.size_specific_replacements
+            .get(icon_name)
+            .is_some_and(|m| m.contains_key(&size));
+        if exists {
+            self.size_specific_links
+                .entry(icon_name.to_string())
+                .or_default()
+                .insert(size, is_link);
+        }
+        exists
+    }
+
+    pub fn set_scaled_replacement_link(&mut self, icon_name: &str, size: u32, scale: u32, is_link: bool) -> bool {
+        let exists = self.scaled_replacements
+            .get(icon_name)
+            .and_then(|m| m.get(&scale))
+            .is_some_and(|m| m.contains_key(&size));
+        if exists {
+            self.scaled_links
+                .entry(icon_name.to_string())
+                .or_default()
+                .entry(scale)
+                .or_default()
+                .insert(size, is_link);
+        }
+        exists
+    }
+
+    // Serves the files of `icon_name` under `alias` as well. Returns false for aliases of
+    // an alias and for names that are aliased themselves, so aliases never chain.
+    pub fn set_alias(&mut self, alias: &str, icon_name: &str) -> bool {
+        if alias == icon_name
+            || self.icon_aliases.contains_key(icon_name)
+            || self.icon_aliases.values().any(|target| target == alias)
+        {
+            return false;
+        }
+        self.icon_aliases.insert(alias.to_string(), icon_name.to_string());
+        true
+    }
+
+    pub fn remove_alias(&mut self, alias: &str) -> bool {
+        self.icon_aliases.remove(alias).is_some()
+    }
+
+    pub fn aliases_of(&self, icon_name: &str) -> Vec<&String> {
+        self.icon_aliases
+            .iter()
+            .filter(|(_, target)| *target == icon_name)
+            .map(|(alias, _)| alias)
+            .collect()
+    }
+
+    // Source files of all replacements, as stored in the project
+    pub fn replacement_paths(&self) -> impl Iterator<Item = &PathBuf> {
+        self.icon_replacements.values()
+            .chain(self.size_specific_replacements.values().flat_map(|m| m.values()))
+            .chain(self.scaled_replacements.values().flat_map(|m| m.values()).flat_map(|m| m.values()))
+    }
+
+    pub fn replacement_paths_mut(&mut self) -> impl Iterator<Item = &mut PathBuf> {
+        self.icon_replacements.values_mut()
+            .chain(self.size_specific_replacements.values_mut().flat_map(|m| m.values_mut()))
+            .chain(self.scaled_replacements.values_mut().flat_map(|m| m.values_mut()).flat_map(|m| m.values_mut()))
+    }
+
+    // Every icon that has any kind of replacement
+    pub fn replaced_icon_names(&self) -> BTreeSet<&String> {
+        self.icon_replacements.keys()
+            .chain(self.size_specific_replacements.keys())
+            .chain(self.scaled_replacements.keys())
+            .collect()
+    }
+}
+
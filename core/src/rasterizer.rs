@@ -0,0 +1,82 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use image::imageops::{self, FilterType};
+use image::{Rgba, Rgba32FImage, RgbaImage};
+use resvg::{tiny_skia, usvg};
+
+// Renders an SVG into a square `pixels`x`pixels` image, keeping the aspect ratio
+// and centering the drawing on a transparent canvas.
+pub fn render_svg(source: &Path, pixels: u32) -> Result<RgbaImage> {
+    let data = std::fs::read(source)
+        .with_context(|| format!("Failed to read {}", source.display()))?;
+    let options = usvg::Options {
+        resources_dir: source.parent().map(Path::to_path_buf),
+        ..Default::default()
+    };
+    let tree = usvg::Tree::from_data(&data, &options)
+        .with_context(|| format!("Failed to parse SVG {}", source.display()))?;
+
+    let mut pixmap = tiny_skia::Pixmap::new(pixels, pixels)
+        .ok_or_else(|| anyhow::anyhow!("Invalid render size {}px", pixels))?;
+    let size = tree.size();
+    let scale = (pixels as f32 / size.width()).min(pixels as f32 / size.height());
+    let offset_x = (pixels as f32 - size.width() * scale) / 2.0;
+    let offset_y = (pixels as f32 - size.height() * scale) / 2.0;
+    let transform = tiny_skia::Transform::from_scale(scale, scale).post_translate(offset_x, offset_y);
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+    // tiny-skia works with premultiplied alpha, PNG expects straight alpha
+    let mut image = RgbaImage::new(pixels, pixels);
+    for (dest, src) in image.pixels_mut().zip(pixmap.pixels()) {
+        let color = src.demultiply();
+        *dest = image::Rgba([color.red(), color.green(), color.blue(), color.alpha()]);
+    }
+    Ok(image)
+}
+
+// Resamples a raster image into a square `pixels`x`pixels` image with a Lanczos filter,
+// keeping the aspect ratio. Images that already have the right size are passed through.
+pub fn resample_image(source: &Path, pixels: u32) -> Result<RgbaImage> {
+    let image = image::open(source)
+        .with_context(|| format!("Failed to decode {}", source.display()))?
+        .into_rgba8();
+    if image.width() == pixels && image.height() == pixels {
+        return Ok(image);
+    }
+
+    let scale = (pixels as f32 / image.width() as f32).min(pixels as f32 / image.height() as f32);
+    let width = ((image.width() as f32 * scale).round() as u32).clamp(1, pixels);
+    let height = ((image.height() as f32 * scale).round() as u32).clamp(1, pixels);
+    // Filtered with premultiplied alpha, otherwise the color of transparent pixels bleeds
+    // into the edges of the drawing
+    let premultiplied = Rgba32FImage::from_fn(image.width(), image.height(), |x, y| {
+        let [r, g, b, a] = image.get_pixel(x, y).0.map(|c| c as f32 / 255.0);
+        Rgba([r * a, g * a, b * a, a])
+    });
+    let filtered = imageops::resize(&premultiplied, width, height, FilterType::Lanczos3);
+    let resized = RgbaImage::from_fn(width, height, |x, y| {
+        let [r, g, b, a] = filtered.get_pixel(x, y).0;
+        // Lanczos rings slightly past the valid range
+        let a = a.clamp(0.0, 1.0);
+        if a == 0.0 {
+            return Rgba([0, 0, 0, 0]);
+        }
+        Rgba([r / a, g / a, b / a, a].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8))
+    });
+
+    let mut canvas = RgbaImage::new(pixels, pixels);
+    imageops::overlay(
+        &mut canvas,
+        &resized,
+        ((pixels - width) / 2) as i64,
+        ((pixels - height) / 2) as i64,
+    );
+    Ok(canvas)
+}
+
+pub fn write_png(image: &RgbaImage, dest: &Path) -> Result<()> {
+    image
+        .save_with_format(dest, image::ImageFormat::Png)
+        .with_context(|| format!("Failed to write {}", dest.display()))
+}
@@ -0,0 +1,104 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+// Directories searched for icon themes, in the order of the Icon Theme Specification:
+// $HOME/.icons, $XDG_DATA_HOME/icons, each of $XDG_DATA_DIRS/icons and /usr/share/pixmaps.
+// Paths the user added come after the data dirs, so installed themes take precedence.
+// Only existing directories are returned, each once.
+pub fn icon_search_paths() -> Vec<PathBuf> {
+    search_paths_with(&load_extra_paths())
+}
+
+pub fn search_paths_with(extra: &[PathBuf]) -> Vec<PathBuf> {
+    candidate_search_paths(extra).into_iter().filter(|path| path.is_dir()).collect()
+}
+
+// Every search path in order, including the ones that don't exist (yet)
+pub fn candidate_search_paths(extra: &[PathBuf]) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    let mut push_unique = |path: PathBuf| {
+        if !dirs.contains(&path) {
+            dirs.push(path);
+        }
+    };
+
+    if let Some(home) = home_dir() {
+        push_unique(home.join(".icons"));
+    }
+    if let Some(data_home) = data_home() {
+        push_unique(data_home.join("icons"));
+    }
+    for dir in data_dirs() {
+        push_unique(dir.join("icons"));
+    }
+    for dir in extra {
+        push_unique(dir.clone());
+    }
+    push_unique(PathBuf::from("/usr/share/pixmaps"));
+
+    dirs
+}
+
+// Where the extra search paths are kept, $XDG_CONFIG_HOME/icon-packer/search-paths.json
+pub fn extra_paths_file() -> Option<PathBuf> {
+    config_home().map(|config| config.join("icon-packer/search-paths.json"))
+}
+
+// A missing or unreadable file means no extra paths
+pub fn load_extra_paths() -> Vec<PathBuf> {
+    let Some(path) = extra_paths_file() else {
+        return Vec::new();
+    };
+    let Ok(content) = fs::read_to_string(&path) else {
+        return Vec::new();
+    };
+    serde_json::from_str(&content).unwrap_or_else(|e| {
+        log::warn!("Ignoring unreadable search path list {}: {}", path.display(), e);
+        Vec::new()
+    })
+}
+
+pub fn save_extra_paths(paths: &[PathBuf]) -> Result<()> {
+    let path = extra_paths_file().context("Neither HOME nor XDG_CONFIG_HOME is set")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let content = serde_json::to_string_pretty(paths)?;
+    fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(PathBuf::from)
+}
+
+// The base directory spec says relative paths in these variables are invalid and must be
+// ignored, an unset or empty variable falls back to the default
+fn absolute_env(name: &str) -> Option<PathBuf> {
+    let value = PathBuf::from(std::env::var_os(name)?);
+    value.is_absolute().then_some(value)
+}
+
+fn data_home() -> Option<PathBuf> {
+    absolute_env("XDG_DATA_HOME").or_else(|| home_dir().map(|home| home.join(".local/share")))
+}
+
+fn config_home() -> Option<PathBuf> {
+    absolute_env("XDG_CONFIG_HOME").or_else(|| home_dir().map(|home| home.join(".config")))
+}
+
+fn data_dirs() -> Vec<PathBuf> {
+    let dirs: Vec<PathBuf> = std::env::var("XDG_DATA_DIRS")
+        .unwrap_or_default()
+        .split(':')
+        .map(Path::new)
+        .filter(|dir| dir.is_absolute())
+        .map(Path::to_path_buf)
+        .collect();
+    if dirs.is_empty() {
+        vec![PathBuf::from("/usr/local/share"), PathBuf::from("/usr/share")]
+    } else {
+        dirs
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex};
+
+use anyhow::{Context, Result};
+use walkdir::WalkDir;
+
+use crate::gtk_icon_cache::CACHE_FILE_NAME;
+use crate::manifest::{GenerationManifest, MANIFEST_FILE_NAME};
+
+// Staging and backup copies live in hidden folders next to the theme rather than being
+// themes of their own, so icon theme scanners (including ours) never pick them up.
+const STAGING_DIR_NAME: &str = ".icon-packer-staging";
+const PREVIOUS_DIR_NAME: &str = ".icon-packer-previous";
+
+// Output directories a generation or restore is working on, see `StagedOutput::lock`
+static BUSY: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+static BUSY_CHANGED: Condvar = Condvar::new();
+
+// Builds a new version of a theme next to the live one and swaps the two directories in
+// one step once it is complete, so readers see either the old theme or the new one. A run
+// only writes the files that changed, the rest of the tree is linked in from the live theme
+// right before the swap. The replaced directory is kept as the previous version, which
+// `restore_previous` swaps back.
+pub struct StagedOutput {
+    output_dir: PathBuf,
+    staging_dir: PathBuf,
+    spare_dir: PathBuf, // Holds the replaced theme where directories can't be exchanged
+    previous_dir: PathBuf,
+}
+
+// Held while working on an output directory, see `StagedOutput::lock`
+pub struct OutputLock {
+    output_dir: PathBuf,
+}
+
+impl Drop for OutputLock {
+    fn drop(&mut self) {
+        BUSY.lock().unwrap().remove(&self.output_dir);
+        BUSY_CHANGED.notify_all();
+    }
+}
+
+impl StagedOutput {
+    pub fn new(output_dir: &Path) -> Self {
+        let parent = output_dir.parent().unwrap_or_else(|| Path::new("."));
+        let name = output_dir
+            .file_name()
+            .map(|n| n.to_os_string())
+            .unwrap_or_else(|| "theme".into());
+        let mut spare_name = name.clone();
+        spare_name.push(".replaced");
+        Self {
+            output_dir: output_dir.to_path_buf(),
+            staging_dir: parent.join(STAGING_DIR_NAME).join(&name),
+            spare_dir: parent.join(STAGING_DIR_NAME).join(spare_name),
+            previous_dir: parent.join(PREVIOUS_DIR_NAME).join(&name),
+        }
+    }
+
+    pub fn staging_dir(&self) -> &Path {
+        &self.staging_dir
+    }
+
+    pub fn has_previous(&self) -> bool {
+        self.previous_dir.is_dir()
+    }
+
+    // Waits until no other generation or restore works on this output directory. Staging
+    // and previous directories are shared by everything writing the same theme, so a run
+    // holds this from `prepare` to `commit`.
+    pub fn lock(&self) -> OutputLock {
+        let mut busy = BUSY.lock().unwrap();
+        while busy.contains(&self.output_dir) {
+            busy = BUSY_CHANGED.wait(busy).unwrap();
+        }
+        busy.insert(self.output_dir.clone());
+        OutputLock { output_dir: self.output_dir.clone() }
+    }
+
+    // Starts an empty staging directory, the generator only writes the files that change
+    pub fn prepare(&self) -> Result<()> {
+        self.discard();
+        fs::create_dir_all(&self.staging_dir)
+            .with_context(|| format!("Failed to create staging directory: {}", self.staging_dir.display()))?;
+        Ok(())
+    }
+
+    // Completes the staged theme with the live files it doesn't replace or delete, lets
+    // `finish` add what needs the whole theme and swaps it in. The replaced theme becomes
+    // the previous version. Returns the deletions that were made.
+    pub fn commit(&self, deletions: &[PathBuf], finish: impl FnOnce(&Path) -> Result<()>) -> Result<Vec<PathBuf>> {
+        let completed = self.complete(deletions).and_then(|removed| finish(&self.staging_dir).map(|()| removed));
+        let removed = match completed {
+            Ok(removed) => removed,
+            Err(e) => {
+                self.discard();
+                return Err(e);
+            }
+        };
+        if fs::symlink_metadata(&self.output_dir).is_err() {
+            // Nothing to replace, an older previous version belongs to another theme
+            remove_dir(&self.previous_dir)?;
+            return move_dir(&self.staging_dir, &self.output_dir).map(|()| removed);
+        }
+        if let Err(e) = swap_dirs(&self.staging_dir, &self.output_dir, &self.spare_dir) {
+            self.discard();
+            return Err(e);
+        }
+        // The new theme is live at this point, losing the old one only costs the restore
+        if let Err(e) = self.keep_previous(&self.staging_dir) {
+            log::warn!("Failed to keep previous version of {}: {:#}", self.output_dir.display(), e);
+            self.discard();
+        }
+        Ok(removed)
+    }
+
+    // Links the live files the run didn't write into the staging directory
+    fn complete(&self, deletions: &[PathBuf]) -> Result<Vec<PathBuf>> {
+        let mut removed = Vec::new();
+        if !self.output_dir.is_dir() {
+            return Ok(removed);
+        }
+        let deletions: BTreeSet<&Path> = deletions.iter().map(PathBuf::as_path).collect();
+        for entry in WalkDir::new(&self.output_dir).min_depth(1).follow_links(false) {
+            let entry = entry?;
+            let relative = entry.path().strip_prefix(&self.output_dir)?;
+            let staged = self.staging_dir.join(relative);
+            if fs::symlink_metadata(&staged).is_ok() {
+                continue;
+            }
+            if entry.file_type().is_dir() {
+                // Directories come along with their files, empty ones were made by hand
+                if is_empty_dir(entry.path()) {
+                    fs::create_dir_all(&staged)
+                        .with_context(|| format!("Failed to create directory: {}", staged.display()))?;
+                }
+                continue;
+            }
+            if deletions.contains(relative) {
+                log::info!("Removed old icon file: {}", entry.path().display());
+                removed.push(relative.to_path_buf());
+                continue;
+            }
+            // Top level files like the project are rewritten in place, a link would change
+            // the previous version along with them
+            link_file(entry.path(), &staged, entry.depth() > 1)?;
+        }
+        removed.sort();
+        Ok(removed)
+    }
+
+    // Swaps the previous version back in, the replaced theme becomes the previous version,
+    // so restoring twice is a no-op. Files that only the live theme has and no run wrote
+    // are kept in the restored one.
+    pub fn restore_previous(&self) -> Result<()> {
+        let _lock = self.lock();
+        if !self.has_previous() {
+            anyhow::bail!("No previous version of {}", self.output_dir.display());
+        }
+        self.discard();
+        if fs::symlink_metadata(&self.output_dir).is_err() {
+            return move_dir(&self.previous_dir, &self.output_dir);
+        }
+        self.carry_over_foreign_files()?;
+        swap_dirs(&self.previous_dir, &self.output_dir, &self.spare_dir)
+    }
+
+    fn carry_over_foreign_files(&self) -> Result<()> {
+        let manifest = GenerationManifest::load(&self.output_dir)?.unwrap_or_default();
+        let generated = |relative: &Path| {
+            manifest.files.contains_key(relative)
+                || [MANIFEST_FILE_NAME, "index.theme", CACHE_FILE_NAME].iter().any(|name| relative == Path::new(name))
+        };
+        for entry in WalkDir::new(&self.output_dir).min_depth(1).follow_links(false) {
+            let entry = entry?;
+            let relative = entry.path().strip_prefix(&self.output_dir)?;
+            let previous = self.previous_dir.join(relative);
+            if entry.file_type().is_dir() || generated(relative) || fs::symlink_metadata(&previous).is_ok() {
+                continue;
+            }
+            link_file(entry.path(), &previous, entry.depth() > 1)?;
+        }
+        Ok(())
+    }
+
+    pub fn discard(&self) {
+        for dir in [&self.staging_dir, &self.spare_dir] {
+            if let Err(e) = remove_dir(dir) {
+                log::warn!("Failed to remove staging directory {}: {:#}", dir.display(), e);
+            }
+        }
+    }
+
+    fn keep_previous(&self, replaced: &Path) -> Result<()> {
+        remove_dir(&self.previous_dir)?;
+        move_dir(replaced, &self.previous_dir)
+    }
+}
+
+// Puts directory `a` where `b` is and the other way around. Linux exchanges them in one
+// step, elsewhere `b` is briefly missing while it is moved through `spare`.
+fn swap_dirs(a: &Path, b: &Path, spare: &Path) -> Result<()> {
+    if exchange(a, b).is_ok() {
+        return Ok(());
+    }
+    remove_dir(spare)?;
+    move_dir(b, spare)?;
+    if let Err(e) = fs::rename(a, b) {
+        // Put the live directory back before giving up
+        fs::rename(spare, b).with_context(|| format!("Failed to move {} back", b.display()))?;
+        return Err(e).with_context(|| format!("Failed to move {} to {}", a.display(), b.display()));
+    }
+    move_dir(spare, a)
+}
+
+#[cfg(target_os = "linux")]
+fn exchange(a: &Path, b: &Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let a = CString::new(a.as_os_str().as_bytes())?;
+    let b = CString::new(b.as_os_str().as_bytes())?;
+    // SAFETY: both paths are valid NUL-terminated strings that outlive the call
+    let result = unsafe {
+        libc::renameat2(libc::AT_FDCWD, a.as_ptr(), libc::AT_FDCWD, b.as_ptr(), libc::RENAME_EXCHANGE)
+    };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn exchange(_a: &Path, _b: &Path) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+fn move_dir(source: &Path, dest: &Path) -> Result<()> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+    fs::rename(source, dest)
+        .with_context(|| format!("Failed to move {} to {}", source.display(), dest.display()))
+}
+
+// Puts `source` at `dest` without copying its data where possible. Generated files are
+// only ever replaced, never written in place, so a hard link can be shared between
+// versions. Symlinks are copied as links.
+fn link_file(source: &Path, dest: &Path, hard_link: bool) -> Result<()> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+    if fs::symlink_metadata(source)?.file_type().is_symlink() {
+        let target = fs::read_link(source)?;
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&target, dest)
+            .with_context(|| format!("Failed to create symlink {} -> {}", dest.display(), target.display()))?;
+        #[cfg(windows)]
+        std::os::windows::fs::symlink_file(&target, dest)
+            .with_context(|| format!("Failed to create symlink {} -> {}", dest.display(), target.display()))?;
+        return Ok(());
+    }
+    if hard_link && fs::hard_link(source, dest).is_ok() {
+        return Ok(());
+    }
+    fs::copy(source, dest)
+        .with_context(|| format!("Failed to copy {} to {}", source.display(), dest.display()))?;
+    Ok(())
+}
+
+fn is_empty_dir(dir: &Path) -> bool {
+    fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_none())
+}
+
+fn remove_dir(dir: &Path) -> Result<()> {
+    if fs::symlink_metadata(dir).is_ok() {
+        fs::remove_dir_all(dir).with_context(|| format!("Failed to remove {}", dir.display()))?;
+    }
+    Ok(())
+}
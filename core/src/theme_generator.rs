@@ -0,0 +1,1005 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::gtk_icon_cache::{self, CacheEntry, CACHE_FILE_NAME};
+use crate::icon_catalog::{is_symbolic, regular_name, IconFormat};
+use crate::icon_theme::DirectoryType;
+use crate::index_theme::{IndexTheme, ICON_THEME_GROUP};
+use crate::manifest::{hash_file, FileStamp, FileState, GenerationManifest, SourceRecord};
+use crate::project::IconProject;
+use crate::rasterizer;
+use crate::staging::StagedOutput;
+use crate::theme_layout::{LayoutSize, ThemeLayout};
+
+pub struct ThemePackGenerator {
+    theme_name: String,
+    theme_comment: String,
+    output_dir: PathBuf,
+    icon_replacements: BTreeMap<String, PathBuf>,
+    size_specific_replacements: BTreeMap<String, BTreeMap<u32, PathBuf>>,
+    icon_links: BTreeMap<String, bool>,
+    size_specific_links: BTreeMap<String, BTreeMap<u32, bool>>,
+    icon_categories: BTreeMap<String, String>, // icon_name -> category
+    scaled_replacements: BTreeMap<String, BTreeMap<u32, BTreeMap<u32, PathBuf>>>, // icon_name -> scale -> size -> path
+    scaled_links: BTreeMap<String, BTreeMap<u32, BTreeMap<u32, bool>>>,
+    icon_aliases: BTreeMap<String, String>, // alias -> icon_name
+    fallback_themes: Vec<String>,
+    layout: ThemeLayout,
+    write_icon_cache: bool,
+    allow_external_links: bool, // Link to the sources themselves instead of a copy inside the theme
+}
+
+// Outcome of a generation run
+#[derive(Debug, Clone, Default)]
+pub struct GenerationReport {
+    pub removed: Vec<PathBuf>,  // Files of earlier runs that were cleaned up
+    pub modified: Vec<PathBuf>, // Files of earlier runs that were kept because they were edited by hand
+    pub unchanged: Vec<PathBuf>, // Files that were already up to date and not rewritten
+    pub index_written: bool,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Theme generation was cancelled")]
+pub struct GenerationCancelled;
+
+// What a generation run will do, computed without touching the disk
+#[derive(Debug, Clone, Default)]
+pub struct GenerationPlan {
+    pub files: Vec<PlannedFile>,
+    pub deletions: Vec<PathBuf>,     // Files of earlier runs that will be removed
+    pub kept_modified: Vec<PathBuf>, // Files of earlier runs that are kept because they were edited by hand
+    pub directories: Vec<GeneratedDirectory>,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlannedFile {
+    pub path: PathBuf, // Relative to the output directory
+    pub source: PathBuf,
+    pub action: FileAction,
+    pub overwrite: bool, // Replaces a file that already exists in the output
+    pub up_to_date: bool, // Output matches the source it was generated from and is left alone
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileAction {
+    Copy,
+    Link, // Absolute symlink to the source, only made when the project allows external links
+    RelativeLink,  // Symlink to another file of the theme, relative to the link
+    Render(u32),   // Rasterize an SVG to the given pixel size
+    Resample(u32), // Resize a raster image to the given pixel size
+}
+
+impl PlannedFile {
+    // One line summary for previews, e.g. "Create 48x48/apps/foo.png (render at 48px from ...)"
+    pub fn describe(&self) -> String {
+        let verb = if self.up_to_date {
+            "Keep"
+        } else if self.overwrite {
+            "Overwrite"
+        } else {
+            "Create"
+        };
+        let action = match self.action {
+            FileAction::Copy => "copy".to_string(),
+            FileAction::Link => "link".to_string(),
+            FileAction::RelativeLink => "relative link".to_string(),
+            FileAction::Render(pixels) => format!("render at {}px", pixels),
+            FileAction::Resample(pixels) => format!("resample to {}px", pixels),
+        };
+        format!("{} {} ({} from {})", verb, self.path.display(), action, self.source.display())
+    }
+}
+
+// A directory that receives icons, as listed in index.theme
+#[derive(Debug, Clone)]
+pub struct GeneratedDirectory {
+    pub name: String,
+    pub entry: LayoutSize,
+    pub scale: u32,
+    pub context: String,
+}
+
+struct Planner<'a> {
+    generator: &'a ThemePackGenerator,
+    files: BTreeMap<PathBuf, PlannedFile>,
+    directories: BTreeMap<String, GeneratedDirectory>,
+    warnings: BTreeSet<String>,
+}
+
+impl Planner<'_> {
+    fn warn(&mut self, warning: String) {
+        self.warnings.insert(warning);
+    }
+
+    // Registers the directory for a size entry and category, returns its relative name
+    fn directory(&mut self, entry: &LayoutSize, scale: u32, category: &str) -> Option<String> {
+        let layout = &self.generator.layout;
+        let Some(context) = layout.context_for_category(category) else {
+            self.warn(format!("Layout has no contexts, cannot place icons of category {}", category));
+            return None;
+        };
+        let name = layout.directory_name(entry, scale, context);
+        self.directories.entry(name.clone()).or_insert_with(|| GeneratedDirectory {
+            name: name.clone(),
+            entry: entry.clone(),
+            scale,
+            context: context.context.clone(),
+        });
+        Some(name)
+    }
+
+    // Registers the symbolic directory of a category, returns its relative name
+    fn symbolic_directory(&mut self, category: &str) -> Option<String> {
+        let layout = &self.generator.layout;
+        let Some(context) = layout.context_for_category(category) else {
+            self.warn(format!("Layout has no contexts, cannot place icons of category {}", category));
+            return None;
+        };
+        let name = layout.symbolic_directory_name(context);
+        self.directories.entry(name.clone()).or_insert_with(|| GeneratedDirectory {
+            name: name.clone(),
+            entry: LayoutSize::symbolic(),
+            scale: 1,
+            context: context.context.clone(),
+        });
+        Some(name)
+    }
+
+    // Places an icon in a fixed-size directory. SVGs are rendered and PNGs resampled
+    // to exactly `pixels`; linked files and XPMs are placed as they are. A linked PNG is
+    // only linked into the directory of its own size, anywhere else it is resampled too.
+    fn place_sized(&mut self, dir_name: &str, icon_name: &str, source: &Path, format: IconFormat, pixels: u32, is_link: bool) {
+        let (ext, action) = match format {
+            IconFormat::Svg if !is_link => ("png", FileAction::Render(pixels)),
+            IconFormat::Png if !is_link || !has_pixel_size(source, pixels) => ("png", FileAction::Resample(pixels)),
+            _ if is_link => (format.extension(), FileAction::Link),
+            _ => (format.extension(), FileAction::Copy),
+        };
+        self.add_file(dir_name, icon_name, ext, source, action);
+    }
+
+    // The link's source is the target's file name, relative to the link
+    fn add_alias(&mut self, target: &Path, alias: &str) {
+        let (Some(file_name), Some(ext)) = (target.file_name(), target.extension()) else {
+            return;
+        };
+        let path = target.with_file_name(format!("{}.{}", alias, ext.to_string_lossy()));
+        if self.files.contains_key(&path) {
+            self.warn(format!("{} has a replacement of its own, not linking it to {}", path.display(), target.display()));
+            return;
+        }
+        self.files.insert(path.clone(), PlannedFile {
+            path,
+            source: PathBuf::from(file_name),
+            action: FileAction::RelativeLink,
+            overwrite: false,
+            up_to_date: false,
+        });
+    }
+
+    // Only projects that allow it link to the source itself, see `resolve_links`
+    fn add_file(&mut self, dir_name: &str, icon_name: &str, ext: &str, source: &Path, action: FileAction) {
+        if !source.exists() {
+            self.warn(format!("Source file is missing: {}", source.display()));
+        }
+        let path = Path::new(dir_name).join(format!("{}.{}", icon_name, ext));
+        if action == FileAction::Link && self.generator.allow_external_links {
+            self.warn(format!(
+                "{} links to {} outside the theme, it only works where that file exists",
+                path.display(),
+                source.display()
+            ));
+        }
+        self.files.insert(path.clone(), PlannedFile {
+            path,
+            source: source.to_path_buf(),
+            action,
+            overwrite: false,
+            up_to_date: false,
+        });
+    }
+
+    // Turns links into relative links to a copy of their source inside the theme. A copy
+    // that is planned anyway is used, otherwise the link in the least scaled directory
+    // becomes the copy.
+    fn resolve_links(&mut self) {
+        if self.generator.allow_external_links {
+            return;
+        }
+        let mut by_source: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+        for file in self.files.values() {
+            if matches!(file.action, FileAction::Copy | FileAction::Link) {
+                by_source.entry(file.source.clone()).or_default().push(file.path.clone());
+            }
+        }
+        for paths in by_source.into_values() {
+            let Some(canonical) = paths
+                .iter()
+                .min_by_key(|path| (self.files[*path].action != FileAction::Copy, self.scale_of(path), *path))
+                .cloned()
+            else {
+                continue;
+            };
+            for path in paths {
+                let Some(file) = self.files.get_mut(&path) else {
+                    continue;
+                };
+                if path == canonical {
+                    file.action = FileAction::Copy;
+                } else if file.action == FileAction::Link {
+                    file.source = relative_path(path.parent().unwrap_or(Path::new("")), &canonical);
+                    file.action = FileAction::RelativeLink;
+                }
+            }
+        }
+    }
+
+    fn scale_of(&self, path: &Path) -> u32 {
+        path.parent()
+            .and_then(|dir| self.directories.get(dir.to_string_lossy().as_ref()))
+            .map_or(1, |dir| dir.scale)
+    }
+}
+
+impl ThemePackGenerator {
+    pub fn new(theme_name: String, output_dir: PathBuf) -> Self {
+        Self {
+            theme_name,
+            theme_comment: "Icon theme generated by icon-packer".to_string(),
+            output_dir,
+            icon_replacements: BTreeMap::new(),
+            size_specific_replacements: BTreeMap::new(),
+            icon_links: BTreeMap::new(),
+            size_specific_links: BTreeMap::new(),
+            icon_categories: BTreeMap::new(),
+            scaled_replacements: BTreeMap::new(),
+            scaled_links: BTreeMap::new(),
+            icon_aliases: BTreeMap::new(),
+            fallback_themes: vec!["hicolor".to_string()],
+            layout: ThemeLayout::default(),
+            write_icon_cache: true,
+            allow_external_links: false,
+        }
+    }
+    
+    // Sets up a generator with everything the project defines
+    pub fn from_project(proj: &IconProject, theme_name: String, output_dir: PathBuf) -> Self {
+        let mut generator = Self::new(theme_name, output_dir);
+
+        let theme_comment = if !proj.theme_comment.is_empty() {
+            proj.theme_comment.clone()
+        } else {
+            "Icon theme generated by icon-packer".to_string()
+        };
+        generator.set_theme_comment(theme_comment);
+        generator.set_layout(proj.layout.clone());
+        generator.set_fallback_themes(proj.fallback_themes.clone());
+        generator.set_write_icon_cache(proj.write_icon_cache);
+        generator.set_allow_external_links(proj.allow_external_links);
+
+        for (icon_name, category) in &proj.icon_categories {
+            generator.set_icon_category(icon_name.clone(), category.clone());
+        }
+
+        for (icon_name, path) in &proj.icon_replacements {
+            generator.add_replacement(icon_name.clone(), proj.resolve_path(path));
+            if let Some(&is_link) = proj.icon_links.get(icon_name) {
+                generator.set_replacement_link(icon_name.clone(), is_link);
+            }
+        }
+
+        for (icon_name, size_map) in &proj.size_specific_replacements {
+            for (size, path) in size_map {
+                generator.add_size_replacement(icon_name.clone(), *size, proj.resolve_path(path));
+                if let Some(&is_link) = proj.size_specific_links.get(icon_name).and_then(|m| m.get(size)) {
+                    generator.set_size_replacement_link(icon_name.clone(), *size, is_link);
+                }
+            }
+        }
+
+        for (icon_name, scale_map) in &proj.scaled_replacements {
+            for (scale, size_map) in scale_map {
+                for (size, path) in size_map {
+                    generator.add_scaled_replacement(icon_name.clone(), *size, *scale, proj.resolve_path(path));
+                    let is_link = proj.scaled_links.get(icon_name)
+                        .and_then(|m| m.get(scale))
+                        .and_then(|m| m.get(size))
+                        .copied();
+                    if let Some(is_link) = is_link {
+                        generator.set_scaled_replacement_link(icon_name.clone(), *size, *scale, is_link);
+                    }
+                }
+            }
+        }
+
+        for (alias, icon_name) in &proj.icon_aliases {
+            generator.add_alias(alias.clone(), icon_name.clone());
+        }
+
+        generator
+    }
+
+    pub fn set_fallback_themes(&mut self, themes: Vec<String>) {
+        self.fallback_themes = themes;
+    }
+    
+    pub fn set_layout(&mut self, layout: ThemeLayout) {
+        self.layout = layout;
+    }
+    
+    pub fn set_write_icon_cache(&mut self, write: bool) {
+        self.write_icon_cache = write;
+    }
+    
+    pub fn set_allow_external_links(&mut self, allow: bool) {
+        self.allow_external_links = allow;
+    }
+    
+    pub fn set_theme_comment(&mut self, comment: String) {
+        self.theme_comment = comment;
+    }
+    
+    pub fn add_size_replacement(&mut self, icon_name: String, size: u32, source_path: PathBuf) {
+        self.size_specific_replacements
+            .entry(icon_name)
+            .or_default()
+            .insert(size, source_path);
+    }
+
+    pub fn add_replacement(&mut self, icon_name: String, source_path: PathBuf) {
+        self.icon_replacements.insert(icon_name, source_path);
+    }
+    
+    pub fn set_replacement_link(&mut self, icon_name: String, is_link: bool) {
+        self.icon_links.insert(icon_name, is_link);
+    }
+    
+    pub fn set_size_replacement_link(&mut self, icon_name: String, size: u32, is_link: bool) {
+        self.size_specific_links
+            .entry(icon_name)
+            .or_default()
+            .insert(size, is_link);
+    }
+    
+    pub fn add_scaled_replacement(&mut self, icon_name: String, size: u32, scale: u32, source_path: PathBuf) {
+        self.scaled_replacements
+            .entry(icon_name)
+            .or_default()
+            .entry(scale)
+            .or_default()
+            .insert(size, source_path);
+    }
+
+    pub fn set_scaled_replacement_link(&mut self, icon_name: String, size: u32, scale: u32, is_link: bool) {
+        self.scaled_links
+            .entry(icon_name)
+            .or_default()
+            .entry(scale)
+            .or_default()
+            .insert(size, is_link);
+    }
+    
+    // Serves every file generated for `icon_name` under `alias` too
+    pub fn add_alias(&mut self, alias: String, icon_name: String) {
+        self.icon_aliases.insert(alias, icon_name);
+    }
+
+    pub fn set_icon_category(&mut self, icon_name: String, category: String) {
+        self.icon_categories.insert(icon_name, category);
+    }
+
+    // Builds the theme in a staging directory and swaps it with the live theme once
+    // everything succeeded. The replaced version is kept, see `StagedOutput::restore_previous`.
+    pub fn generate(&self) -> Result<GenerationReport> {
+        self.generate_with_progress(|_, _| true)
+    }
+
+    // Like `generate`, reporting (done, total) files as it goes. Returning false from
+    // `progress` stops the run with `GenerationCancelled` and leaves the live theme untouched.
+    pub fn generate_with_progress(&self, mut progress: impl FnMut(usize, usize) -> bool) -> Result<GenerationReport> {
+        let plan = self.plan()?;
+        if !progress(0, plan.files.len()) {
+            return Err(GenerationCancelled.into());
+        }
+        for warning in &plan.warnings {
+            log::warn!("{}", warning);
+        }
+        let staged = StagedOutput::new(&self.output_dir);
+        let _lock = staged.lock();
+        staged.prepare()?;
+        let mut report = match self.execute(&plan, staged.staging_dir(), &mut progress) {
+            Ok(report) => report,
+            Err(e) => {
+                staged.discard();
+                return Err(e);
+            }
+        };
+        // The cache lists the whole theme, so it is written once the staged theme is complete
+        report.removed = staged.commit(&plan.deletions, |staging_dir| self.generate_icon_cache(staging_dir))?;
+        progress(plan.files.len(), plan.files.len());
+        Ok(report)
+    }
+
+    // Works out what `generate` would do without writing anything
+    pub fn plan(&self) -> Result<GenerationPlan> {
+        let mut planner = Planner {
+            generator: self,
+            files: BTreeMap::new(),
+            directories: BTreeMap::new(),
+            warnings: BTreeSet::new(),
+        };
+
+        // Symbolic icons get a single SVG in their symbolic directory, however they were replaced
+        let symbolic_names: BTreeSet<&String> = self.icon_replacements.keys()
+            .chain(self.size_specific_replacements.keys())
+            .chain(self.scaled_replacements.keys())
+            .filter(|icon_name| is_symbolic(icon_name))
+            .collect();
+        for icon_name in symbolic_names {
+            self.plan_symbolic(&mut planner, icon_name);
+        }
+
+        // Process size-specific replacements
+        for (icon_name, size_map) in &self.size_specific_replacements {
+            if is_symbolic(icon_name) {
+                continue;
+            }
+            let category = self.category_of(icon_name);
+            for (size, source_path) in size_map {
+                let format = IconFormat::from_path(source_path);
+                let Some(entry) = self.layout.sized_entry(*size) else {
+                    planner.warn(format!("Layout has no {}px directory for {}, skipping", size, icon_name));
+                    continue;
+                };
+                if format == IconFormat::Other {
+                    planner.warn(format!("Unsupported format for {}: {}", icon_name, source_path.display()));
+                    continue;
+                }
+                if let Some(dir_name) = planner.directory(entry, 1, &category) {
+                    let is_link = self.size_specific_links
+                        .get(icon_name)
+                        .and_then(|m| m.get(size))
+                        .copied()
+                        .unwrap_or(false);
+                    if is_link && format == IconFormat::Png && !has_pixel_size(source_path, *size) {
+                        planner.warn(format!("Linked {}px replacement for {} is not {}px, resampling it", size, icon_name, size));
+                    }
+                    planner.place_sized(&dir_name, icon_name, source_path, format, *size, is_link);
+                }
+            }
+        }
+
+        // Process HiDPI replacements into e.g. 16x16@2/{context}/
+        for (icon_name, scale_map) in &self.scaled_replacements {
+            if is_symbolic(icon_name) {
+                continue;
+            }
+            let category = self.category_of(icon_name);
+            for (scale, size_map) in scale_map {
+                for (size, source_path) in size_map {
+                    let format = IconFormat::from_path(source_path);
+                    let Some(entry) = self.layout.sized_entry(*size) else {
+                        planner.warn(format!("Layout has no {}px directory for {}@{}x, skipping", size, icon_name, scale));
+                        continue;
+                    };
+                    if format == IconFormat::Other {
+                        planner.warn(format!("Unsupported format for {}: {}", icon_name, source_path.display()));
+                        continue;
+                    }
+                    if let Some(dir_name) = planner.directory(entry, *scale, &category) {
+                        let is_link = self.scaled_links
+                            .get(icon_name)
+                            .and_then(|m| m.get(scale))
+                            .and_then(|m| m.get(size))
+                            .copied()
+                            .unwrap_or(false);
+                        if is_link && format == IconFormat::Png && !has_pixel_size(source_path, size * scale) {
+                            planner.warn(format!(
+                                "Linked {}px@{}x replacement for {} is not {}px, resampling it",
+                                size, scale, icon_name, size * scale
+                            ));
+                        }
+                        planner.place_sized(&dir_name, icon_name, source_path, format, size * scale, is_link);
+                    }
+                }
+            }
+        }
+        
+        // Process general replacements (not size-specific)
+        for (icon_name, source_path) in &self.icon_replacements {
+            // Skip if this icon has size-specific replacements
+            if self.size_specific_replacements.contains_key(icon_name) || is_symbolic(icon_name) {
+                continue;
+            }
+            
+            let category = self.category_of(icon_name);
+            let format = IconFormat::from_path(source_path);
+            let is_link = self.icon_links.get(icon_name).copied().unwrap_or(false);
+
+            match format {
+                IconFormat::Svg => {
+                    // Place SVG in the scalable directories
+                    for entry in self.layout.scalable_entries() {
+                        if let Some(dir_name) = planner.directory(entry, 1, &category) {
+                            let action = if is_link { FileAction::Link } else { FileAction::Copy };
+                            planner.add_file(&dir_name, icon_name, "svg", source_path, action);
+                        }
+                    }
+
+                    // Render crisp PNGs for apps that ignore scalable directories
+                    for entry in self.layout.sized_entries() {
+                        if let Some(dir_name) = planner.directory(entry, 1, &category) {
+                            planner.place_sized(&dir_name, icon_name, source_path, format, entry.size, false);
+                        }
+                    }
+                }
+                IconFormat::Png => {
+                    if is_link && !self.layout.sized_entries().any(|entry| has_pixel_size(source_path, entry.size)) {
+                        planner.warn(format!("Linked replacement for {} fits no directory size, resampling it", icon_name));
+                    }
+                    // Place PNG in all sized directories
+                    for entry in self.layout.sized_entries() {
+                        if let Some(dir_name) = planner.directory(entry, 1, &category) {
+                            planner.place_sized(&dir_name, icon_name, source_path, format, entry.size, is_link);
+                        }
+                    }
+                }
+                _ => {
+                    planner.warn(format!("Unsupported format for {}: {:?}", icon_name, format));
+                }
+            }
+        }
+
+        planner.resolve_links();
+
+        // Aliases link to each file of their icon from the same directory
+        for (alias, icon_name) in &self.icon_aliases {
+            let targets: Vec<PathBuf> = planner.files
+                .keys()
+                .filter(|path| path.file_stem().is_some_and(|stem| *stem == **icon_name))
+                .cloned()
+                .collect();
+            if targets.is_empty() {
+                planner.warn(format!("Alias {} refers to {}, which has no replacement", alias, icon_name));
+                continue;
+            }
+            for target in targets {
+                planner.add_alias(&target, alias);
+            }
+        }
+
+        let Planner { files, directories, mut warnings, .. } = planner;
+        let mut plan = GenerationPlan {
+            files: files.into_values().collect(),
+            directories: directories.into_values().collect(),
+            ..Default::default()
+        };
+
+        let previous = GenerationManifest::load(&self.output_dir)?;
+        let mut source_hashes = HashMap::new();
+        for file in &mut plan.files {
+            let output = self.output_dir.join(&file.path);
+            file.overwrite = fs::symlink_metadata(&output).is_ok();
+            if let Some(ref previous) = previous {
+                file.up_to_date = file.overwrite && is_up_to_date(previous, file, &output, &mut source_hashes);
+            }
+        }
+
+        // Files of the previous run are the only ones cleanup may remove
+        if let Some(ref previous) = previous {
+            let planned: BTreeSet<&PathBuf> = plan.files.iter().map(|f| &f.path).collect();
+            for (relative, recorded) in &previous.files {
+                if planned.contains(relative) {
+                    continue;
+                }
+                let path = self.output_dir.join(relative);
+                if fs::symlink_metadata(&path).is_err() {
+                    continue;
+                }
+                match FileState::read(&path) {
+                    Ok(state) if state == *recorded => plan.deletions.push(relative.clone()),
+                    Ok(_) => {
+                        // Changed by hand since it was generated, leave it to the user
+                        warnings.insert(format!("Keeping modified file that is no longer generated: {}", path.display()));
+                        plan.kept_modified.push(relative.clone());
+                    }
+                    Err(e) => {
+                        warnings.insert(format!("Failed to check old icon file {}: {:?}", path.display(), e));
+                    }
+                }
+            }
+        }
+        plan.warnings = warnings.into_iter().collect();
+
+        Ok(plan)
+    }
+
+    // Writes the files that changed into `staging_dir`, the commit links in everything else
+    fn execute(
+        &self,
+        plan: &GenerationPlan,
+        staging_dir: &Path,
+        progress: &mut dyn FnMut(usize, usize) -> bool,
+    ) -> Result<GenerationReport> {
+        let previous = GenerationManifest::load(&self.output_dir)?.unwrap_or_default();
+        let mut manifest = GenerationManifest::default();
+        let mut report = GenerationReport {
+            modified: plan.kept_modified.clone(),
+            ..Default::default()
+        };
+        let mut source_hashes = HashMap::new();
+        for (index, file) in plan.files.iter().enumerate() {
+            if !progress(index, plan.files.len()) {
+                return Err(GenerationCancelled.into());
+            }
+            if file.up_to_date && fs::symlink_metadata(self.output_dir.join(&file.path)).is_ok() {
+                manifest.carry_over(&previous, &file.path);
+                report.unchanged.push(file.path.clone());
+                continue;
+            }
+            let dest = staging_dir.join(&file.path);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+            }
+            write_file(file, &dest)?;
+            manifest.record(staging_dir, file.path.clone())?;
+
+            // A relative link's source is relative to the link, there is no source file to track
+            let (source_stamp, source_sha256) = match file.action {
+                FileAction::RelativeLink => (None, None),
+                _ => (
+                    FileStamp::read(&file.source).ok(),
+                    source_hashes
+                        .entry(file.source.clone())
+                        .or_insert_with(|| hash_file(&file.source).ok())
+                        .clone(),
+                ),
+            };
+            manifest.sources.insert(file.path.clone(), SourceRecord {
+                source: file.source.clone(),
+                action: file.action,
+                source_stamp,
+                source_sha256,
+                output_stamp: match file.action {
+                    FileAction::Link | FileAction::RelativeLink => None,
+                    _ => Some(FileStamp::read(&dest)?),
+                },
+            });
+        }
+
+        // Files of earlier runs that are no longer generated are removed by the commit
+        manifest.directories = plan.directories.iter().map(|dir| dir.name.clone()).collect();
+        manifest.save(staging_dir)?;
+
+        report.index_written = self.generate_index_theme(staging_dir, &plan.directories, &previous.generated_directories())?;
+
+        Ok(report)
+    }
+
+    // Rendering would lose the recoloring, so symbolic icons are SVGs placed as they are.
+    // The replacement for all sizes is used, otherwise the one closest to the symbolic size.
+    fn plan_symbolic(&self, planner: &mut Planner<'_>, icon_name: &str) {
+        let mut candidates: Vec<(Option<u32>, &PathBuf, bool)> = Vec::new(); // (pixels, source, is_link)
+        if let Some(source_path) = self.icon_replacements.get(icon_name) {
+            candidates.push((None, source_path, self.icon_links.get(icon_name).copied().unwrap_or(false)));
+        }
+        for (size, source_path) in self.size_specific_replacements.get(icon_name).into_iter().flatten() {
+            let is_link = self.size_specific_links.get(icon_name).and_then(|m| m.get(size)).copied().unwrap_or(false);
+            candidates.push((Some(*size), source_path, is_link));
+        }
+        for (scale, size_map) in self.scaled_replacements.get(icon_name).into_iter().flatten() {
+            for (size, source_path) in size_map {
+                let is_link = self.scaled_links.get(icon_name)
+                    .and_then(|m| m.get(scale))
+                    .and_then(|m| m.get(size))
+                    .copied()
+                    .unwrap_or(false);
+                candidates.push((Some(size * scale), source_path, is_link));
+            }
+        }
+        candidates.retain(|(_, source_path, _)| {
+            let is_svg = IconFormat::from_path(source_path) == IconFormat::Svg;
+            if !is_svg {
+                planner.warn(format!("Symbolic icon {} needs an SVG, skipping {}", icon_name, source_path.display()));
+            }
+            is_svg
+        });
+        let symbolic_size = LayoutSize::symbolic().size;
+        candidates.sort_by_key(|(pixels, ..)| pixels.map(|pixels| pixels.abs_diff(symbolic_size) + 1));
+        let Some(((_, source_path, is_link), rest)) = candidates.split_first() else {
+            return;
+        };
+        for (_, ignored, _) in rest.iter().filter(|(_, ignored, _)| ignored != source_path) {
+            planner.warn(format!("Symbolic icon {} has one SVG for all sizes, ignoring {}", icon_name, ignored.display()));
+        }
+        if let Some(dir_name) = planner.symbolic_directory(&self.category_of(icon_name)) {
+            let action = if *is_link { FileAction::Link } else { FileAction::Copy };
+            planner.add_file(&dir_name, icon_name, "svg", source_path, action);
+        }
+    }
+
+    // Symbolic icons without a category of their own go where their full color icon goes
+    fn category_of(&self, icon_name: &str) -> String {
+        self.icon_categories.get(icon_name)
+            .or_else(|| regular_name(icon_name).and_then(|name| self.icon_categories.get(name)))
+            .cloned()
+            .unwrap_or_else(|| "Applications".to_string())
+    }
+    
+    // Returns whether index.theme had to be written, it is left alone when nothing in it changed
+    fn generate_index_theme(
+        &self,
+        staging_dir: &Path,
+        dir_defs: &[GeneratedDirectory],
+        previous_directories: &BTreeSet<String>,
+    ) -> Result<bool> {
+        let existing = fs::read_to_string(self.output_dir.join("index.theme")).ok();
+        let content = self.index_theme_content(existing.as_deref(), dir_defs, previous_directories);
+        if existing.is_some_and(|existing| existing == content) {
+            return Ok(false);
+        }
+        let index_path = staging_dir.join("index.theme");
+        fs::write(&index_path, content)
+            .with_context(|| format!("Failed to write index.theme: {}", index_path.display()))?;
+
+        Ok(true)
+    }
+
+    // GTK only uses the cache while it is newer than the theme directory, so unlike
+    // index.theme it is written on every run, as the last file of the staged theme.
+    fn generate_icon_cache(&self, target_dir: &Path) -> Result<()> {
+        let cache_path = target_dir.join(CACHE_FILE_NAME);
+        if !self.write_icon_cache {
+            // A leftover cache would hide icons added later
+            if cache_path.exists() {
+                fs::remove_file(&cache_path)
+                    .with_context(|| format!("Failed to remove {}", cache_path.display()))?;
+            }
+            return Ok(());
+        }
+
+        // Covers the directories index.theme lists, not only the generated ones
+        let index = IndexTheme::load(&target_dir.join("index.theme"))?;
+        let directories: Vec<String> = index.get_list(ICON_THEME_GROUP, "Directories")
+            .into_iter()
+            .chain(index.get_list(ICON_THEME_GROUP, "ScaledDirectories"))
+            .collect();
+        let mut entries = Vec::new();
+        for directory in &directories {
+            let Ok(dir_entries) = fs::read_dir(target_dir.join(directory)) else {
+                continue;
+            };
+            let mut formats: BTreeMap<String, Vec<IconFormat>> = BTreeMap::new();
+            for entry in dir_entries {
+                let path = entry?.path();
+                let format = IconFormat::from_path(&path);
+                let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+                    continue;
+                };
+                if format != IconFormat::Other {
+                    formats.entry(stem.to_string()).or_default().push(format);
+                }
+            }
+            for (name, mut formats) in formats {
+                formats.sort_by_key(|format| format.extension());
+                entries.push(CacheEntry {
+                    name,
+                    directory: directory.clone(),
+                    formats,
+                });
+            }
+        }
+        let data = gtk_icon_cache::encode(&directories, &entries)?;
+        // Renamed into place, GTK may read the old one meanwhile
+        let partial = target_dir.join(format!(".{}.partial", CACHE_FILE_NAME));
+        fs::write(&partial, data)
+            .with_context(|| format!("Failed to write {}", partial.display()))?;
+        fs::rename(&partial, &cache_path)
+            .with_context(|| format!("Failed to move {} to {}", partial.display(), cache_path.display()))?;
+        // The rename touched the directory after the cache was written
+        fs::File::options()
+            .write(true)
+            .open(&cache_path)
+            .and_then(|file| file.set_modified(SystemTime::now()))
+            .with_context(|| format!("Failed to update {}", cache_path.display()))?;
+        Ok(())
+    }
+
+    // Edits the existing index.theme rather than replacing it, so keys we don't write
+    // ourselves (translations, Example, KDE's extensions, comments) survive regeneration.
+    // Directories of the theme that an earlier run didn't generate stay listed as well.
+    fn index_theme_content(
+        &self,
+        existing: Option<&str>,
+        dir_defs: &[GeneratedDirectory],
+        previous_directories: &BTreeSet<String>,
+    ) -> String {
+        let mut index = existing.map(IndexTheme::parse).unwrap_or_default();
+
+        // Icon Theme section
+        let is_foreign = |name: &String| {
+            !previous_directories.contains(name) && !dir_defs.iter().any(|dir| dir.name == *name)
+        };
+        let mut directories_list: Vec<String> = dir_defs.iter()
+            .filter(|dir| dir.scale == 1)
+            .map(|dir| dir.name.clone())
+            .collect();
+        directories_list.extend(index.get_list(ICON_THEME_GROUP, "Directories").into_iter().filter(is_foreign));
+        // HiDPI directories go into ScaledDirectories so implementations without scale support ignore them
+        let mut scaled_directories_list: Vec<String> = dir_defs.iter()
+            .filter(|dir| dir.scale > 1)
+            .map(|dir| dir.name.clone())
+            .collect();
+        scaled_directories_list.extend(index.get_list(ICON_THEME_GROUP, "ScaledDirectories").into_iter().filter(is_foreign));
+        // Use theme_name if provided, otherwise use a default
+        let theme_display_name = if !self.theme_name.is_empty() {
+            self.theme_name.as_str()
+        } else {
+            "Custom Icon Theme"
+        };
+        // Use configured fallback themes
+        let inherits = if self.fallback_themes.is_empty() {
+            "hicolor".to_string()
+        } else {
+            self.fallback_themes.join(",")
+        };
+
+        // Sections of directories an earlier run generated and this one doesn't
+        for name in previous_directories {
+            if !dir_defs.iter().any(|dir| dir.name == *name) {
+                index.remove_group(name);
+            }
+        }
+
+        index.set(ICON_THEME_GROUP, "Name", theme_display_name);
+        index.set(ICON_THEME_GROUP, "Comment", &self.theme_comment);
+        index.set(ICON_THEME_GROUP, "Inherits", &inherits);
+        index.set(ICON_THEME_GROUP, "Directories", &directories_list.join(","));
+        if scaled_directories_list.is_empty() {
+            index.remove(ICON_THEME_GROUP, "ScaledDirectories");
+        } else {
+            index.set(ICON_THEME_GROUP, "ScaledDirectories", &scaled_directories_list.join(","));
+        }
+
+        // Directory sections
+        for dir in dir_defs {
+            let group = dir.name.as_str();
+            index.set(group, "Size", &dir.entry.size.to_string());
+            if dir.scale > 1 {
+                index.set(group, "Scale", &dir.scale.to_string());
+            } else {
+                index.remove(group, "Scale");
+            }
+            index.set(group, "Context", &dir.context);
+            index.set(group, "Type", match dir.entry.dir_type {
+                DirectoryType::Fixed => "Fixed",
+                DirectoryType::Scaled => "Scaled",
+                DirectoryType::Threshold => "Threshold",
+            });
+
+            match dir.entry.dir_type {
+                DirectoryType::Scaled => {
+                    index.set(group, "MinSize", &dir.entry.min_size.unwrap_or(1).to_string());
+                    index.set(group, "MaxSize", &dir.entry.max_size.unwrap_or(256).to_string());
+                    index.remove(group, "Threshold");
+                }
+                DirectoryType::Threshold => {
+                    match dir.entry.threshold {
+                        Some(threshold) => index.set(group, "Threshold", &threshold.to_string()),
+                        None => index.remove(group, "Threshold"),
+                    }
+                    index.remove(group, "MinSize");
+                    index.remove(group, "MaxSize");
+                }
+                DirectoryType::Fixed => {
+                    index.remove(group, "MinSize");
+                    index.remove(group, "MaxSize");
+                    index.remove(group, "Threshold");
+                }
+            }
+        }
+
+        index.to_string()
+    }
+}
+
+fn write_file(file: &PlannedFile, dest: &Path) -> Result<()> {
+    let source = &file.source;
+    match file.action {
+        FileAction::Copy => {
+            fs::copy(source, dest)
+                .with_context(|| format!("Failed to copy {} to {}", source.display(), dest.display()))?;
+            log::info!("Copied {} -> {}", source.display(), dest.display());
+        }
+        FileAction::Link => {
+            symlink(source, dest)?;
+            log::info!("Linked {} -> {}", dest.display(), source.display());
+        }
+        FileAction::RelativeLink => {
+            symlink(source, dest)?;
+            log::info!("Linked {} -> {} (relative)", dest.display(), source.display());
+        }
+        FileAction::Render(pixels) => {
+            rasterizer::write_png(&rasterizer::render_svg(source, pixels)?, dest)?;
+            log::info!("Rendered {} -> {} ({}px)", source.display(), dest.display(), pixels);
+        }
+        FileAction::Resample(pixels) => {
+            rasterizer::write_png(&rasterizer::resample_image(source, pixels)?, dest)?;
+            log::info!("Resampled {} -> {} ({}px)", source.display(), dest.display(), pixels);
+        }
+    }
+    Ok(())
+}
+
+fn symlink(source: &Path, dest: &Path) -> Result<()> {
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(source, dest)
+        .with_context(|| format!("Failed to create symlink {} -> {}", dest.display(), source.display()))?;
+    #[cfg(windows)]
+    std::os::windows::fs::symlink_file(source, dest)
+        .with_context(|| format!("Failed to create symlink {} -> {}", dest.display(), source.display()))?;
+    Ok(())
+}
+
+// Path of `target` as seen from `from_dir`, both relative to the output directory
+fn relative_path(from_dir: &Path, target: &Path) -> PathBuf {
+    let common = from_dir.components().zip(target.components()).take_while(|(a, b)| a == b).count();
+    let mut relative = PathBuf::new();
+    for _ in from_dir.components().skip(common) {
+        relative.push("..");
+    }
+    relative.extend(target.components().skip(common));
+    relative
+}
+
+// An output can be kept when it was made from the same source the same way, the source
+// still has the same content and the output hasn't been touched since.
+fn is_up_to_date(
+    previous: &GenerationManifest,
+    file: &PlannedFile,
+    output: &Path,
+    source_hashes: &mut HashMap<PathBuf, Option<String>>,
+) -> bool {
+    let (Some(record), Some(state)) = (previous.sources.get(&file.path), previous.files.get(&file.path)) else {
+        return false;
+    };
+    if record.source != file.source || record.action != file.action {
+        return false;
+    }
+
+    let output_intact = match state {
+        FileState::Link(target) => fs::read_link(output).is_ok_and(|current| current == *target),
+        FileState::Sha256(_) => {
+            record.output_stamp.is_some()
+                && !fs::symlink_metadata(output).is_ok_and(|m| m.file_type().is_symlink())
+                && FileStamp::read(output).ok() == record.output_stamp
+        }
+    };
+    if !output_intact {
+        return false;
+    }
+    if matches!(file.action, FileAction::Link | FileAction::RelativeLink) {
+        return true;
+    }
+
+    if record.source_stamp.is_some() && FileStamp::read(&file.source).ok() == record.source_stamp {
+        return true;
+    }
+    // Touched but possibly identical, e.g. after a checkout
+    let hash = source_hashes
+        .entry(file.source.clone())
+        .or_insert_with(|| hash_file(&file.source).ok());
+    hash.is_some() && *hash == record.source_sha256
+}
+
+fn has_pixel_size(path: &Path, pixels: u32) -> bool {
+    image::image_dimensions(path).is_ok_and(|dimensions| dimensions == (pixels, pixels))
+}
+
@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+
+use crate::icon_theme::DirectoryType;
+
+// Describes which directories a generated theme consists of and how they are named.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThemeLayout {
+    pub sizes: Vec<LayoutSize>,
+    pub naming: DirectoryNaming,
+    pub contexts: Vec<LayoutContext>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayoutSize {
+    pub size: u32,
+    pub dir_type: DirectoryType,
+    #[serde(default)]
+    pub min_size: Option<u32>,
+    #[serde(default)]
+    pub max_size: Option<u32>,
+    #[serde(default)]
+    pub threshold: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DirectoryNaming {
+    SizeFirst,    // 48x48/apps, scalable/apps, 48x48@2/apps
+    ContextFirst, // apps/48, apps/scalable, apps/48@2x
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayoutContext {
+    pub category: String,  // Category as used in the project, e.g. "Applications"
+    pub directory: String, // Directory name, e.g. "apps"
+    pub context: String,   // Context= value in index.theme, e.g. "Applications"
+}
+
+impl Default for ThemeLayout {
+    fn default() -> Self {
+        Self {
+            sizes: [16, 22, 32, 48, 64, 128]
+                .into_iter()
+                .map(LayoutSize::fixed)
+                .chain(std::iter::once(LayoutSize::scalable(48, 1, 256)))
+                .collect(),
+            naming: DirectoryNaming::SizeFirst,
+            contexts: default_contexts(),
+        }
+    }
+}
+
+impl ThemeLayout {
+    pub fn breeze() -> Self {
+        Self {
+            sizes: [16, 22, 24, 32, 48, 64, 96, 128, 256]
+                .into_iter()
+                .map(LayoutSize::fixed)
+                .chain(std::iter::once(LayoutSize::scalable(48, 8, 512)))
+                .collect(),
+            naming: DirectoryNaming::ContextFirst,
+            contexts: default_contexts(),
+        }
+    }
+
+    pub fn preset_name(&self) -> &'static str {
+        if *self == Self::default() {
+            "default"
+        } else if *self == Self::breeze() {
+            "breeze"
+        } else {
+            "custom"
+        }
+    }
+
+    pub fn from_preset(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Self::default()),
+            "breeze" => Some(Self::breeze()),
+            _ => None,
+        }
+    }
+
+    // Sizes that hold pixel-exact icons (everything but Scaled directories)
+    pub fn sized_entries(&self) -> impl Iterator<Item = &LayoutSize> {
+        self.sizes.iter().filter(|s| s.dir_type != DirectoryType::Scaled)
+    }
+
+    pub fn scalable_entries(&self) -> impl Iterator<Item = &LayoutSize> {
+        self.sizes.iter().filter(|s| s.dir_type == DirectoryType::Scaled)
+    }
+
+    pub fn sized_entry(&self, size: u32) -> Option<&LayoutSize> {
+        self.sized_entries().find(|s| s.size == size)
+    }
+
+    // Unknown categories fall back to the first context, like the old "apps" default
+    pub fn context_for_category(&self, category: &str) -> Option<&LayoutContext> {
+        self.contexts
+            .iter()
+            .find(|c| c.category == category)
+            .or_else(|| self.contexts.first())
+    }
+
+    pub fn directory_name(&self, entry: &LayoutSize, scale: u32, context: &LayoutContext) -> String {
+        let size_part = match (self.naming, entry.dir_type) {
+            (_, DirectoryType::Scaled) => "scalable".to_string(),
+            (DirectoryNaming::SizeFirst, _) => format!("{}x{}", entry.size, entry.size),
+            (DirectoryNaming::ContextFirst, _) => entry.size.to_string(),
+        };
+        let size_part = match (scale, self.naming) {
+            (1, _) => size_part,
+            (_, DirectoryNaming::SizeFirst) => format!("{}@{}", size_part, scale),
+            (_, DirectoryNaming::ContextFirst) => format!("{}@{}x", size_part, scale),
+        };
+        match self.naming {
+            DirectoryNaming::SizeFirst => format!("{}/{}", size_part, context.directory),
+            DirectoryNaming::ContextFirst => format!("{}/{}", context.directory, size_part),
+        }
+    }
+
+    // Symbolic icons stay SVGs so desktops can recolor them, one directory per context
+    // holds them at every size: symbolic/apps, or apps/symbolic with context first naming
+    pub fn symbolic_directory_name(&self, context: &LayoutContext) -> String {
+        match self.naming {
+            DirectoryNaming::SizeFirst => format!("symbolic/{}", context.directory),
+            DirectoryNaming::ContextFirst => format!("{}/symbolic", context.directory),
+        }
+    }
+
+    // Every directory this layout can produce for the given scales
+    pub fn all_directory_names(&self, scales: &[u32]) -> Vec<String> {
+        let mut names = Vec::new();
+        for context in &self.contexts {
+            for entry in &self.sizes {
+                for scale in scales {
+                    names.push(self.directory_name(entry, *scale, context));
+                }
+            }
+            names.push(self.symbolic_directory_name(context));
+        }
+        names
+    }
+}
+
+impl LayoutSize {
+    pub fn fixed(size: u32) -> Self {
+        Self {
+            size,
+            dir_type: DirectoryType::Fixed,
+            min_size: None,
+            max_size: None,
+            threshold: None,
+        }
+    }
+
+    // The size Adwaita declares for its symbolic directories
+    pub fn symbolic() -> Self {
+        Self::scalable(16, 8, 512)
+    }
+
+    pub fn scalable(size: u32, min_size: u32, max_size: u32) -> Self {
+        Self {
+            size,
+            dir_type: DirectoryType::Scaled,
+            min_size: Some(min_size),
+            max_size: Some(max_size),
+            threshold: None,
+        }
+    }
+}
+
+fn default_contexts() -> Vec<LayoutContext> {
+    [
+        ("Applications", "apps", "Applications"),
+        ("Mimetypes", "mimetypes", "MimeTypes"),
+        ("Actions", "actions", "Actions"),
+        ("Places", "places", "Places"),
+        ("Devices", "devices", "Devices"),
+        ("Status", "status", "Status"),
+    ]
+    .into_iter()
+    .map(|(category, directory, context)| LayoutContext {
+        category: category.to_string(),
+        directory: directory.to_string(),
+        context: context.to_string(),
+    })
+    .collect()
+}
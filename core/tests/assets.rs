@@ -0,0 +1,79 @@
+mod common;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use icon_packer_core::assets::{self, ASSETS_DIR_NAME};
+use icon_packer_core::project::IconProject;
+use icon_packer_core::theme_generator::ThemePackGenerator;
+
+#[test]
+fn imports_each_file_once_under_its_hash() {
+    let tmp = tempfile::tempdir().unwrap();
+    let project_dir = tmp.path().join("Shared");
+    let copy = tmp.path().join("RED.SVG");
+    fs::copy(common::source("red.svg"), &copy).unwrap();
+
+    let first = assets::import_asset(&project_dir, &common::source("red.svg")).unwrap();
+    let second = assets::import_asset(&project_dir, &copy).unwrap();
+
+    assert_eq!(first, second);
+    assert!(first.starts_with(ASSETS_DIR_NAME));
+    assert_eq!(first.extension().unwrap(), "svg");
+    assert_eq!(fs::read(project_dir.join(&first)).unwrap(), fs::read(&copy).unwrap());
+    assert_eq!(fs::read_dir(project_dir.join(ASSETS_DIR_NAME)).unwrap().count(), 1);
+}
+
+#[test]
+fn vendored_projects_work_from_anywhere() {
+    let tmp = tempfile::tempdir().unwrap();
+    let project_dir = tmp.path().join("Shared");
+    let mut project = IconProject::new("Shared".to_string());
+    project.output_path = Some(project_dir.clone());
+    project.set_replacement("editor", common::source("red.svg"));
+    project.set_size_replacement("viewer", 16, common::source("blue.png"));
+    project.set_scaled_replacement("viewer", 16, 2, common::source("red.svg"));
+
+    assert_eq!(assets::import_assets(&mut project, &project_dir, &mut BTreeMap::new()).unwrap(), 3);
+    assert!(project.replacement_paths().all(|path| path.is_relative()));
+    assert_eq!(project.icon_replacements["editor"], project.scaled_replacements["viewer"][&2][&16]);
+
+    let moved = tmp.path().join("Moved");
+    fs::rename(&project_dir, &moved).unwrap();
+    project.output_path = Some(moved.clone());
+    let plan = ThemePackGenerator::from_project(&project, "Shared".to_string(), moved.clone()).plan().unwrap();
+    assert!(plan.warnings.is_empty(), "{:?}", plan.warnings);
+    assert!(plan.files.iter().all(|file| file.source.starts_with(moved.join(ASSETS_DIR_NAME))));
+}
+
+#[test]
+fn garbage_collection_keeps_only_referenced_assets() {
+    let tmp = tempfile::tempdir().unwrap();
+    let project_dir = tmp.path().join("Shared");
+    let mut project = IconProject::new("Shared".to_string());
+    project.vendor_assets = true;
+    project.set_replacement("editor", common::source("red.svg"));
+    project.set_replacement("viewer", common::source("blue.png"));
+    project.set_replacement("folder", common::source("red.svg"));
+    assets::import_assets(&mut project, &project_dir, &mut BTreeMap::new()).unwrap();
+    let blue = project.icon_replacements["viewer"].clone();
+    let red = project.icon_replacements["editor"].clone();
+    // Files the user keeps in the folder aren't part of the store
+    let foreign = project_dir.join(ASSETS_DIR_NAME).join("notes.txt");
+    fs::write(&foreign, "by hand").unwrap();
+
+    project.vendor_assets = false;
+    assert!(assets::collect_garbage(&project, &project_dir).unwrap().is_empty());
+    project.vendor_assets = true;
+
+    project.remove_replacement("viewer");
+    project.remove_replacement("editor");
+    assert_eq!(assets::collect_garbage(&project, &project_dir).unwrap(), vec![blue]);
+    assert!(project_dir.join(&red).is_file());
+
+    project.remove_replacement("folder");
+    assert_eq!(assets::collect_garbage(&project, &project_dir).unwrap(), vec![red]);
+    assert!(foreign.is_file());
+    assert!(assets::collect_garbage(&project, Path::new("missing")).unwrap().is_empty());
+}
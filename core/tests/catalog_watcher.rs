@@ -0,0 +1,64 @@
+mod common;
+
+use std::fs;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use icon_packer_core::catalog_cache::CatalogCache;
+use icon_packer_core::catalog_watcher::CatalogWatcher;
+use icon_packer_core::icon_catalog::IconCatalog;
+
+#[test]
+fn picks_up_icons_added_and_removed_on_disk() {
+    let tmp = tempfile::tempdir().unwrap();
+    common::copy_tree(&common::themes_dir(), &tmp.path().join("icons"));
+    std::env::set_var("HOME", tmp.path().join("home"));
+    std::env::remove_var("XDG_DATA_HOME");
+    std::env::remove_var("XDG_CONFIG_HOME");
+    std::env::set_var("XDG_DATA_DIRS", tmp.path());
+
+    let (_, cache) = IconCatalog::discover_with_cache(None, &CatalogCache::default()).unwrap();
+    let (sender, receiver) = mpsc::channel();
+    let _watcher = CatalogWatcher::new(cache, move |catalog, _| {
+        let names: Vec<String> = catalog.iter().map(|icon| icon.name.clone()).collect();
+        sender.send(names).unwrap();
+    })
+    .unwrap();
+    let has = |names: &[String], name: &str| names.iter().any(|n| n == name);
+
+    fs::write(tmp.path().join("icons/Basic/48x48/apps/installed.png"), "").unwrap();
+    let names = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert!(has(&names, "installed"));
+    assert!(has(&names, "editor"));
+
+    fs::remove_file(tmp.path().join("icons/Minimal/icons/minimal.png")).unwrap();
+    let names = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert!(!has(&names, "minimal"));
+    assert!(has(&names, "installed"));
+
+    // A theme installed later is watched as well
+    let fresh = tmp.path().join("Fresh");
+    fs::create_dir_all(fresh.join("icons")).unwrap();
+    fs::write(fresh.join("index.theme"), "[Icon Theme]\nName=Fresh\nDirectories=icons\n\n[icons]\nSize=24\n").unwrap();
+    fs::write(fresh.join("icons/fresh.png"), "").unwrap();
+    fs::rename(&fresh, tmp.path().join("icons/Fresh")).unwrap();
+    let names = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert!(has(&names, "fresh"));
+
+    fs::remove_file(tmp.path().join("icons/Fresh/icons/fresh.png")).unwrap();
+    let names = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert!(!has(&names, "fresh"));
+
+    // The first theme in ~/.local/share/icons, which didn't exist at the start
+    let late = tmp.path().join("late");
+    fs::create_dir_all(late.join(".local/share/icons/Late/icons")).unwrap();
+    fs::write(late.join(".local/share/icons/Late/index.theme"), "[Icon Theme]\nName=Late\nDirectories=icons\n\n[icons]\nSize=24\n").unwrap();
+    fs::write(late.join(".local/share/icons/Late/icons/late.png"), "").unwrap();
+    fs::rename(&late, tmp.path().join("home")).unwrap();
+    let names = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert!(has(&names, "late"));
+
+    fs::write(tmp.path().join("home/.local/share/icons/Late/icons/later.png"), "").unwrap();
+    let names = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert!(has(&names, "later"));
+}
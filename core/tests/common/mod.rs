@@ -0,0 +1,70 @@
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use walkdir::WalkDir;
+
+pub fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+pub fn themes_dir() -> PathBuf {
+    fixtures_dir().join("themes")
+}
+
+pub fn source(name: &str) -> PathBuf {
+    fixtures_dir().join("sources").join(name)
+}
+
+pub fn copy_tree(source: &Path, dest: &Path) {
+    for entry in WalkDir::new(source) {
+        let entry = entry.unwrap();
+        let target = dest.join(entry.path().strip_prefix(source).unwrap());
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target).unwrap();
+        } else {
+            fs::copy(entry.path(), &target).unwrap();
+        }
+    }
+}
+
+// Lists every entry of a generated theme, one per line, skipping our hidden bookkeeping
+// files. Symlinks are marked, links to sources only by file name since their targets depend
+// on where the repo is checked out.
+pub fn describe_tree(root: &Path) -> String {
+    let mut lines = Vec::new();
+    for entry in WalkDir::new(root).min_depth(1).sort_by_file_name() {
+        let entry = entry.unwrap();
+        let relative = entry.path().strip_prefix(root).unwrap();
+        if relative.to_string_lossy().starts_with('.') || entry.file_type().is_dir() {
+            continue;
+        }
+        let mut line = relative.to_string_lossy().to_string();
+        if entry.path_is_symlink() {
+            line.push_str(" -> ");
+            let target = fs::read_link(entry.path()).unwrap();
+            if target.is_absolute() {
+                line.push_str(&target.file_name().unwrap().to_string_lossy());
+            } else {
+                line.push_str(&target.to_string_lossy());
+            }
+        }
+        lines.push(line);
+    }
+    lines.join("\n") + "\n"
+}
+
+// Compares `actual` against tests/golden/<case>/<file>. Run with UPDATE_GOLDEN=1 to
+// rewrite the golden files after an intended change.
+pub fn assert_golden(case: &str, file: &str, actual: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(case).join(file);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|_| panic!("Missing golden file {}, run with UPDATE_GOLDEN=1", path.display()));
+    assert_eq!(expected, actual, "{} differs from the generated output", path.display());
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use icon_packer_core::gtk_icon_cache::{GtkIconCache, CACHE_FILE_NAME};
+use icon_packer_core::icon_catalog::{IconCatalog, IconFormat};
+
+fn fixture() -> PathBuf {
+    common::fixtures_dir().join("caches/basic.cache")
+}
+
+fn set_modified(path: &Path, time: SystemTime) {
+    File::options().write(true).open(path).unwrap().set_modified(time).unwrap();
+}
+
+fn variant_paths(catalog: &IconCatalog, icon: &str) -> Vec<PathBuf> {
+    catalog.theme("Basic").unwrap().variants(icon).iter().map(|v| v.path.clone()).collect()
+}
+
+#[test]
+fn parses_directories_and_icon_chains() {
+    let cache = GtkIconCache::load(&fixture()).unwrap();
+
+    assert_eq!(
+        cache.directories().unwrap(),
+        vec!["16x16/apps", "48x48/apps", "scalable/apps", "32x32/places", "unlisted/apps"]
+    );
+    let mut entries = cache.entries().unwrap();
+    entries.sort_by(|a, b| (&a.name, &a.directory).cmp(&(&b.name, &b.directory)));
+    let names: Vec<(&str, &str)> = entries.iter().map(|e| (e.name.as_str(), e.directory.as_str())).collect();
+    assert_eq!(
+        names,
+        vec![
+            ("editor", "16x16/apps"),
+            ("editor", "48x48/apps"),
+            ("folder", "32x32/places"),
+            ("hidden", "unlisted/apps"),
+            ("phantom", "48x48/apps"),
+            ("viewer", "scalable/apps"),
+        ]
+    );
+    assert_eq!(entries[4].formats, vec![IconFormat::Png, IconFormat::Svg]);
+}
+
+#[test]
+fn rejects_damaged_caches() {
+    let data = fs::read(fixture()).unwrap();
+
+    let mut wrong_version = data.clone();
+    wrong_version[1] = 2;
+    assert!(GtkIconCache::parse(wrong_version).is_err());
+    let truncated = GtkIconCache::parse(data[..100].to_vec()).unwrap();
+    assert!(truncated.entries().is_err());
+}
+
+// Changes the environment, so the catalog part has to stay in this one test
+#[test]
+fn catalog_uses_fresh_caches_only() {
+    let tmp = tempfile::tempdir().unwrap();
+    common::copy_tree(&common::themes_dir(), &tmp.path().join("icons"));
+    std::env::set_var("HOME", tmp.path().join("home"));
+    std::env::remove_var("XDG_DATA_HOME");
+    std::env::remove_var("XDG_CONFIG_HOME");
+    std::env::set_var("XDG_DATA_DIRS", tmp.path());
+    let cache = tmp.path().join("icons/Basic").join(CACHE_FILE_NAME);
+    fs::copy(fixture(), &cache).unwrap();
+
+    // Outdated, so the directories are walked
+    set_modified(&cache, SystemTime::UNIX_EPOCH);
+    let walked = IconCatalog::discover(None).unwrap();
+    assert!(variant_paths(&walked, "phantom").is_empty());
+
+    // The cache lists an icon that isn't on disk, which shows it was used
+    set_modified(&cache, SystemTime::now() + Duration::from_secs(60));
+    let cached = IconCatalog::discover(None).unwrap();
+    let basic = tmp.path().join("icons/Basic");
+    assert_eq!(
+        variant_paths(&cached, "phantom"),
+        vec![basic.join("48x48/apps/phantom.png"), basic.join("48x48/apps/phantom.svg")]
+    );
+    // Directories missing from index.theme are skipped, the rest matches walking
+    assert!(variant_paths(&cached, "hidden").is_empty());
+    for icon in ["editor", "folder", "viewer"] {
+        assert_eq!(variant_paths(&cached, icon), variant_paths(&walked, icon), "{}", icon);
+    }
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use icon_packer_core::catalog_cache::CatalogCache;
+use icon_packer_core::icon_catalog::{IconCatalog, IconFormat, IconMetadata, UNTHEMED};
+
+// /usr/share/pixmaps is always searched, leave out whatever the machine has there
+fn from_fixtures(icon: &IconMetadata) -> bool {
+    icon.variants.iter().any(|v| !v.path.starts_with("/usr/share/pixmaps"))
+}
+
+fn names(catalog: &IconCatalog) -> Vec<String> {
+    catalog.iter().filter(from_fixtures).map(|icon| icon.name).collect()
+}
+
+fn has(catalog: &IconCatalog, name: &str) -> bool {
+    catalog.iter().any(|icon| icon.name == name)
+}
+
+// Everything runs in one test since the search path comes from the environment
+#[test]
+fn cache_is_reused_until_a_theme_changes() {
+    let tmp = tempfile::tempdir().unwrap();
+    common::copy_tree(&common::themes_dir(), &tmp.path().join("icons"));
+    std::env::set_var("HOME", tmp.path().join("home"));
+    std::env::remove_var("XDG_DATA_HOME");
+    std::env::remove_var("XDG_CONFIG_HOME");
+    std::env::set_var("XDG_DATA_DIRS", tmp.path());
+
+    let (catalog, cache) = IconCatalog::discover_with_cache(None, &CatalogCache::default()).unwrap();
+    assert_eq!(
+        names(&catalog),
+        vec!["editor", "fallback", "folder", "legacy-app", "minimal", "parent-only", "viewer", "viewer-symbolic"]
+    );
+    let editor = catalog.iter().find(|icon| icon.name == "editor").unwrap();
+    // Themes in name order, then directories in index.theme order
+    let paths: Vec<_> = editor.variants.iter().map(|v| v.path.strip_prefix(tmp.path()).unwrap()).collect();
+    assert_eq!(
+        paths,
+        vec![
+            Path::new("icons/Basic/16x16/apps/editor.png"),
+            Path::new("icons/Basic/48x48/apps/editor.png"),
+            Path::new("icons/HiDPI/16x16/apps/editor.png"),
+            Path::new("icons/HiDPI/16x16@2/apps/editor.png"),
+        ]
+    );
+
+    // Per theme, the icons of Basic and everything it looks up through
+    let basic = catalog.theme("Basic Fixture").unwrap();
+    assert_eq!(basic.directory_name, "Basic");
+    assert_eq!(basic.icon_names().collect::<Vec<_>>(), vec!["editor", "folder", "viewer", "viewer-symbolic"]);
+    // Icons are paired with their symbolic version
+    assert_eq!(basic.symbolic_variants("viewer").len(), 1);
+    assert!(basic.symbolic_variants("editor").is_empty());
+    let sizes: Vec<(u32, u32)> = catalog.theme("HiDPI").unwrap().variants_by_size("editor").into_keys().collect();
+    assert_eq!(sizes, vec![(16, 1), (16, 2)]);
+    let chain: Vec<&str> = catalog
+        .inheritance_chain(&["Basic".to_string()])
+        .iter()
+        .map(|theme| theme.directory_name.as_str())
+        .collect();
+    assert_eq!(chain, vec!["Basic", "Parent", "hicolor"]);
+    let visible: Vec<_> = catalog.icons_for_themes(&["Basic".to_string()]).filter(from_fixtures).collect();
+    let visible_names: Vec<&str> = visible.iter().map(|icon| icon.name.as_str()).collect();
+    assert_eq!(
+        visible_names,
+        vec!["editor", "fallback", "folder", "legacy-app", "parent-only", "viewer", "viewer-symbolic"]
+    );
+    assert!(visible[5].symbolic[0].path.ends_with("scalable/apps/viewer-symbolic.svg"));
+    assert!(visible[6].symbolic.is_empty());
+    assert!(visible[0].variants.iter().all(|v| v.theme_name == "Basic Fixture"));
+
+    // Files lying directly in a search path form the unthemed source
+    let unthemed = catalog.unthemed();
+    assert!(unthemed.icon_names().any(|name| name == "legacy-app"));
+    assert_eq!(unthemed.variants("legacy-app")[0].format, IconFormat::Xpm);
+    assert_eq!(visible[3].variants[0].theme_name, UNTHEMED);
+
+    // Unchanged themes come from the cache, even if it disagrees with the disk
+    let mut stale = cache.clone();
+    for theme in &mut stale.themes {
+        theme.icons.remove("minimal");
+    }
+    let (catalog, _) = IconCatalog::discover_with_cache(None, &stale).unwrap();
+    assert!(!has(&catalog, "minimal"));
+
+    // Adding an icon changes its directory's mtime, so that theme is rescanned
+    fs::write(tmp.path().join("icons/Minimal/icons/added.png"), "").unwrap();
+    let (catalog, cache) = IconCatalog::discover_with_cache(None, &stale).unwrap();
+    assert!(has(&catalog, "minimal"));
+    assert!(has(&catalog, "added"));
+
+    // Removed themes drop out of the cache
+    fs::remove_dir_all(tmp.path().join("icons/HiDPI")).unwrap();
+    let (_, cache) = IconCatalog::discover_with_cache(None, &cache).unwrap();
+    assert_eq!(cache.themes.len(), 4);
+
+    let cache_path = tmp.path().join("cache/catalog.json");
+    cache.save(&cache_path).unwrap();
+    assert_eq!(CatalogCache::load(&cache_path).themes.len(), 4);
+    assert!(CatalogCache::load(&tmp.path().join("missing.json")).themes.is_empty());
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use icon_packer_core::icon_lookup::{fallback_names, IconLookup};
+
+fn lookup() -> IconLookup {
+    IconLookup::new(vec![common::themes_dir()])
+}
+
+#[test]
+fn prefers_exact_size_then_closest() {
+    let themes = common::themes_dir();
+    let mut lookup = lookup();
+
+    assert_eq!(lookup.find_icon("editor", 48, 1, "Basic"), Some(themes.join("Basic/48x48/apps/editor.png")));
+    assert_eq!(lookup.find_icon("editor", 20, 1, "Basic"), Some(themes.join("Basic/16x16/apps/editor.png")));
+    // Scalable directories match every size in their range
+    assert_eq!(lookup.find_icon("viewer", 128, 1, "Basic"), Some(themes.join("Basic/scalable/apps/viewer.svg")));
+    // Within the threshold of 4 counts as an exact match
+    assert_eq!(lookup.find_icon("folder", 28, 1, "Basic"), Some(themes.join("Basic/32x32/places/folder.png")));
+}
+
+#[test]
+fn follows_inherits_and_hicolor_without_looping() {
+    let themes = common::themes_dir();
+    let mut lookup = lookup();
+
+    // Basic and Parent inherit from each other
+    assert_eq!(lookup.find_icon("parent-only", 22, 1, "Basic"), Some(themes.join("Parent/22x22/apps/parent-only.png")));
+    assert_eq!(lookup.find_icon("fallback", 48, 1, "Basic"), Some(themes.join("hicolor/48x48/apps/fallback.png")));
+    // Icons outside any theme are the last resort
+    assert_eq!(lookup.find_icon("legacy-app", 48, 1, "Basic"), Some(themes.join("legacy-app.xpm")));
+    assert_eq!(lookup.find_icon("missing", 48, 1, "Basic"), None);
+}
+
+#[test]
+fn picks_directories_by_scale() {
+    let themes = common::themes_dir();
+    let mut lookup = lookup();
+
+    assert_eq!(lookup.find_icon("editor", 16, 2, "HiDPI"), Some(themes.join("HiDPI/16x16@2/apps/editor.png")));
+    assert_eq!(lookup.find_icon("editor", 16, 1, "HiDPI"), Some(themes.join("HiDPI/16x16/apps/editor.png")));
+}
+
+#[test]
+fn symbolic_names_fall_back_to_the_full_color_icon() {
+    let themes = common::themes_dir();
+    let mut lookup = lookup();
+
+    assert_eq!(
+        lookup.find_icon("viewer-symbolic", 16, 1, "Basic"),
+        Some(themes.join("Basic/scalable/apps/viewer-symbolic.svg"))
+    );
+    assert_eq!(lookup.find_icon("editor-symbolic", 16, 1, "Basic"), Some(themes.join("Basic/16x16/apps/editor.png")));
+    // Parents and the unthemed icons are searched for both names too
+    assert_eq!(
+        lookup.find_icon("parent-only-symbolic", 22, 1, "Basic"),
+        Some(themes.join("Parent/22x22/apps/parent-only.png"))
+    );
+    assert_eq!(lookup.find_icon("legacy-app-symbolic", 48, 1, "Basic"), Some(themes.join("legacy-app.xpm")));
+}
+
+#[test]
+fn drops_dash_separated_details() {
+    let themes = common::themes_dir();
+    let mut lookup = lookup();
+
+    assert_eq!(
+        fallback_names("network-wireless-signal-good"),
+        vec!["network-wireless-signal-good", "network-wireless-signal", "network-wireless", "network"]
+    );
+    assert_eq!(fallback_names("go-up-symbolic"), vec!["go-up-symbolic", "go-symbolic", "go-up", "go"]);
+    assert_eq!(lookup.find_icon("editor-new-document", 48, 1, "Basic"), Some(themes.join("Basic/48x48/apps/editor.png")));
+    assert_eq!(
+        lookup.find_icon("parent-only-detail", 22, 1, "Basic"),
+        Some(themes.join("Parent/22x22/apps/parent-only.png"))
+    );
+    assert_eq!(lookup.find_icon("legacy-app-large", 48, 1, "Basic"), Some(themes.join("legacy-app.xpm")));
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use std::fs;
+
+use icon_packer_core::icon_theme::{DirectoryType, IconThemeDefinition};
+use icon_packer_core::index_theme::{IndexTheme, ICON_THEME_GROUP};
+
+#[test]
+fn loads_theme_metadata_and_directories() {
+    let theme = IconThemeDefinition::load_from_directory(&common::themes_dir().join("Basic")).unwrap();
+
+    assert_eq!(theme.directory_name, "Basic");
+    assert_eq!(theme.name, "Basic Fixture");
+    assert_eq!(theme.inherits, vec!["Parent"]);
+    let keys: Vec<&str> = theme.directories.iter().map(|d| d.key.as_str()).collect();
+    assert_eq!(keys, vec!["16x16/apps", "48x48/apps", "scalable/apps", "32x32/places"]);
+
+    let scalable = &theme.directories[2];
+    assert_eq!(scalable.dir_type, DirectoryType::Scaled);
+    assert_eq!(scalable.size, Some(48));
+    assert_eq!(scalable.min_size, Some(8));
+    assert_eq!(scalable.max_size, Some(512));
+    assert_eq!(scalable.context, "Applications");
+
+    let places = &theme.directories[3];
+    assert_eq!(places.dir_type, DirectoryType::Threshold);
+    assert_eq!(places.threshold, Some(4));
+    assert_eq!(places.context, "Places");
+}
+
+#[test]
+fn reads_scaled_directories() {
+    let theme = IconThemeDefinition::load_from_directory(&common::themes_dir().join("HiDPI")).unwrap();
+
+    let scales: Vec<(&str, u32)> = theme.directories.iter().map(|d| (d.key.as_str(), d.scale)).collect();
+    assert_eq!(scales, vec![("16x16/apps", 1), ("16x16@2/apps", 2)]);
+}
+
+#[test]
+fn fills_in_defaults_for_missing_keys() {
+    let theme = IconThemeDefinition::load_from_directory(&common::themes_dir().join("Minimal")).unwrap();
+
+    assert_eq!(theme.name, "Minimal");
+    assert!(theme.inherits.is_empty());
+    let dir = &theme.directories[0];
+    assert_eq!(dir.size, Some(24));
+    assert_eq!(dir.dir_type, DirectoryType::Threshold);
+    assert_eq!(dir.context, "Generic");
+    assert_eq!(dir.scale, 1);
+}
+
+#[test]
+fn fails_without_index_theme() {
+    let dir = tempfile::tempdir().unwrap();
+
+    assert!(IconThemeDefinition::load_from_directory(dir.path()).is_err());
+}
+
+#[test]
+fn keeps_every_key_of_index_theme() {
+    let content = fs::read_to_string(common::fixtures_dir().join("index/kde.theme")).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("index.theme"), &content).unwrap();
+
+    let theme = IconThemeDefinition::load_from_directory(dir.path()).unwrap();
+
+    assert_eq!(theme.comment.as_deref(), Some("Dark fixture theme"));
+    assert_eq!(theme.example.as_deref(), Some("folder"));
+    assert!(!theme.hidden);
+    assert_eq!(theme.localized_name("de_DE.UTF-8"), "Fixture Dunkel");
+    assert_eq!(theme.localized_name("pt_BR"), "Fixture Escuro");
+    assert_eq!(theme.localized_name("fr_FR"), "Fixture Dark");
+    assert_eq!(theme.localized_comment("de"), Some("Dunkles Testthema"));
+    assert_eq!(theme.index.get(ICON_THEME_GROUP, "FollowsColorScheme"), Some("true"));
+    assert_eq!(theme.index.get(ICON_THEME_GROUP, "DesktopDefault"), Some("48"));
+    assert_eq!(theme.directories[2].scale, 2);
+    assert_eq!(theme.index.to_string(), content);
+}
+
+#[test]
+fn edits_index_theme_in_place() {
+    let mut index = IndexTheme::parse("[Icon Theme]\nName=Old\nX-Custom = kept  \n\n[a]\nSize=16\n\n[b]\nSize=32\n");
+
+    index.set(ICON_THEME_GROUP, "Name", "New");
+    index.set(ICON_THEME_GROUP, "Comment", "Added");
+    index.set("c", "Size", "48");
+    index.remove_group("b");
+
+    assert_eq!(
+        index.to_string(),
+        "[Icon Theme]\nName=New\nX-Custom = kept  \nComment=Added\n\n[a]\nSize=16\n\n[c]\nSize=48\n"
+    );
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use icon_packer_core::assets::ASSETS_DIR_NAME;
+use icon_packer_core::live_generation::{LiveEvent, LiveGenerationWorker, LiveJob};
+use icon_packer_core::project::{IconProject, PROJECT_FILE_NAME};
+
+// Waits for the end of the next run, returns whether it succeeded and what it imported
+fn finish(events: &mpsc::Receiver<LiveEvent>) -> (bool, Option<Vec<PathBuf>>) {
+    let mut imported = None;
+    loop {
+        match events.recv_timeout(Duration::from_secs(30)).unwrap() {
+            LiveEvent::AssetsImported { imported: paths, .. } => imported = Some(paths.into_values().collect()),
+            LiveEvent::Finished(_) => return (true, imported),
+            LiveEvent::Failed(_) => return (false, imported),
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn vendored_sources_survive_the_original_being_deleted() {
+    let tmp = tempfile::tempdir().unwrap();
+    let project_dir = tmp.path().join("Shared");
+    let original = tmp.path().join("editor.svg");
+    fs::copy(common::source("red.svg"), &original).unwrap();
+    let mut project = IconProject::new("Shared".to_string());
+    project.output_path = Some(project_dir.clone());
+    project.vendor_assets = true;
+    project.set_replacement("editor", original.clone());
+    let job = |project: &IconProject| LiveJob { project: project.clone(), output_path: project_dir.clone() };
+
+    let (sender, events) = mpsc::channel();
+    let worker = LiveGenerationWorker::new(move |event| {
+        let _ = sender.send(event);
+    });
+    worker.submit(job(&project));
+    let (finished, imported) = finish(&events);
+    assert!(finished);
+    let stored = imported.unwrap().remove(0);
+    assert!(stored.starts_with(ASSETS_DIR_NAME));
+
+    // The GUI may not have taken the stored path over yet when the next edit comes in
+    fs::remove_file(&original).unwrap();
+    worker.submit(job(&project));
+    assert!(finish(&events).0);
+
+    assert!(project_dir.join(&stored).is_file());
+    assert!(project_dir.join("scalable/apps/editor.svg").is_file());
+    let saved = IconProject::load(&project_dir.join(PROJECT_FILE_NAME)).unwrap();
+    assert_eq!(saved.icon_replacements["editor"], stored);
+}
+
+#[test]
+fn exports_run_on_the_worker_and_survive_later_edits() {
+    let tmp = tempfile::tempdir().unwrap();
+    let project_dir = tmp.path().join("Shared");
+    let export_dir = tmp.path().join("Exported");
+    let mut project = IconProject::new("Shared".to_string());
+    project.output_path = Some(project_dir.clone());
+    project.set_replacement("editor", common::source("red.svg"));
+
+    let (sender, events) = mpsc::channel();
+    let worker = LiveGenerationWorker::new(move |event| {
+        let _ = sender.send(event);
+    });
+    worker.export(LiveJob { project: project.clone(), output_path: export_dir.clone() }, "Exported".to_string());
+    // An edit right after must neither cancel nor swallow the export
+    worker.submit(LiveJob { project: project.clone(), output_path: project_dir.clone() });
+
+    let mut exported = None;
+    let mut finished = false;
+    while exported.is_none() || !finished {
+        match events.recv_timeout(Duration::from_secs(30)).unwrap() {
+            LiveEvent::Exported(path) => exported = Some(path),
+            LiveEvent::Finished(_) => finished = true,
+            LiveEvent::Failed(message) => panic!("{}", message),
+            _ => {}
+        }
+    }
+    assert_eq!(exported.unwrap(), export_dir);
+    assert!(fs::read_to_string(export_dir.join("index.theme")).unwrap().contains("Name=Exported"));
+    // Exports leave the project where it lives
+    assert!(!export_dir.join(PROJECT_FILE_NAME).exists());
+    assert!(project_dir.join(PROJECT_FILE_NAME).is_file());
+}
@@ -0,0 +1,33 @@
+mod common;
+
+use icon_packer_core::project::{IconProject, PROJECT_FILE_NAME};
+
+#[test]
+fn round_trips_replacements_and_forgets_links_of_removed_ones() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mut project = IconProject::new("Round Trip".to_string());
+    project.set_replacement("editor", common::source("red.svg"));
+    project.set_size_replacement("viewer", 16, common::source("blue.png"));
+    project.set_scaled_replacement("viewer", 16, 2, common::source("red.svg"));
+    assert!(project.set_size_replacement_link("viewer", 16, true));
+    assert!(project.set_scaled_replacement_link("viewer", 16, 2, true));
+
+    let path = tmp.path().join(PROJECT_FILE_NAME);
+    project.save(&path).unwrap();
+    let mut loaded = IconProject::load(&path).unwrap();
+    assert_eq!(loaded.icon_replacements, project.icon_replacements);
+    assert!(loaded.size_specific_links["viewer"][&16]);
+    assert!(loaded.scaled_links["viewer"][&2][&16]);
+
+    // Adding the size again starts out as a copy
+    assert!(loaded.remove_size_replacement("viewer", 16));
+    assert!(loaded.remove_scaled_replacement("viewer", 16, 2));
+    assert!(!loaded.remove_size_replacement("viewer", 16));
+    assert!(!loaded.remove_replacement("viewer"));
+    loaded.set_size_replacement("viewer", 16, common::source("blue.png"));
+    loaded.set_scaled_replacement("viewer", 16, 2, common::source("red.svg"));
+    loaded.save(&path).unwrap();
+    let reloaded = IconProject::load(&path).unwrap();
+    assert!(!reloaded.size_specific_links.contains_key("viewer"));
+    assert!(!reloaded.scaled_links.contains_key("viewer"));
+}
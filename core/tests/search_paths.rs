@@ -0,0 +1,40 @@
+use std::fs;
+use std::path::PathBuf;
+
+use icon_packer_core::search_paths::{icon_search_paths, load_extra_paths, save_extra_paths};
+
+// Everything runs in one test since the search path comes from the environment
+#[test]
+fn follows_xdg_order_and_appends_extra_paths() {
+    let tmp = tempfile::tempdir().unwrap();
+    let home = tmp.path().join("home");
+    let data = tmp.path().join("data");
+    let share = tmp.path().join("share");
+    let extra = tmp.path().join("team-assets");
+    for dir in [home.join(".icons"), home.join(".local/share/icons"), data.join("icons"), share.join("icons"), extra.clone()] {
+        fs::create_dir_all(dir).unwrap();
+    }
+    std::env::set_var("HOME", &home);
+    std::env::remove_var("XDG_DATA_HOME");
+    std::env::remove_var("XDG_CONFIG_HOME");
+    // Relative entries are invalid and skipped, missing ones are left out
+    std::env::set_var(
+        "XDG_DATA_DIRS",
+        format!("{}:relative/share:{}:{}", data.display(), tmp.path().join("missing").display(), share.display()),
+    );
+
+    let expected = vec![home.join(".icons"), home.join(".local/share/icons"), data.join("icons"), share.join("icons")];
+    assert!(icon_search_paths().starts_with(&expected));
+
+    assert!(load_extra_paths().is_empty());
+    save_extra_paths(&[extra.clone(), data.join("icons")]).unwrap();
+    assert_eq!(load_extra_paths(), vec![extra.clone(), data.join("icons")]);
+    let mut with_extra = expected.clone();
+    with_extra.push(extra);
+    assert!(icon_search_paths().starts_with(&with_extra));
+
+    // XDG_DATA_HOME replaces ~/.local/share
+    std::env::set_var("XDG_DATA_HOME", &data);
+    let paths: Vec<PathBuf> = icon_search_paths();
+    assert_eq!(paths[..3], [home.join(".icons"), data.join("icons"), share.join("icons")]);
+}
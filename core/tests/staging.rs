@@ -0,0 +1,90 @@
+mod common;
+
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+use icon_packer_core::staging::StagedOutput;
+use icon_packer_core::theme_generator::ThemePackGenerator;
+
+fn inode(path: &Path) -> u64 {
+    fs::symlink_metadata(path).unwrap().ino()
+}
+
+#[test]
+fn swaps_whole_versions_and_restores_both_ways() {
+    let tmp = tempfile::tempdir().unwrap();
+    let output = tmp.path().join("Incremental");
+    let generator = |folder: bool| {
+        let mut generator = ThemePackGenerator::new("Incremental".to_string(), output.clone());
+        generator.add_replacement("editor".to_string(), common::source("red.svg"));
+        if folder {
+            generator.add_size_replacement("folder".to_string(), 16, common::source("legacy.xpm"));
+            generator.set_icon_category("folder".to_string(), "Places".to_string());
+        }
+        generator
+    };
+    generator(false).generate().unwrap();
+    fs::write(output.join("handmade.txt"), "by hand").unwrap();
+    let editor = output.join("16x16/apps/editor.png");
+    let editor_inode = inode(&editor);
+
+    let report = generator(true).generate().unwrap();
+    assert_eq!(report.unchanged.len(), 7);
+    // Untouched files are neither copied nor linked again
+    assert_eq!(inode(&editor), editor_inode);
+    assert!(output.join("16x16/places/folder.xpm").is_file());
+    assert!(fs::read_to_string(output.join("index.theme")).unwrap().contains("16x16/places"));
+    assert_eq!(fs::read_to_string(output.join("handmade.txt")).unwrap(), "by hand");
+    // The cache came in with the rest of the theme and GTK takes it as current
+    let modified = |path: &Path| fs::metadata(path).unwrap().modified().unwrap();
+    assert!(modified(&output.join("icon-theme.cache")) >= modified(&output));
+
+    let staged = StagedOutput::new(&output);
+    // Added after the run, it stays in whichever version is live
+    fs::write(output.join("later.txt"), "later").unwrap();
+    staged.restore_previous().unwrap();
+    assert!(output.join("later.txt").is_file());
+    assert!(!output.join("16x16/places/folder.xpm").exists());
+    assert!(!output.join("16x16/places").exists());
+    assert!(!fs::read_to_string(output.join("index.theme")).unwrap().contains("16x16/places"));
+    assert_eq!(inode(&editor), editor_inode);
+
+    staged.restore_previous().unwrap();
+    assert!(output.join("16x16/places/folder.xpm").is_file());
+    assert!(fs::read_to_string(output.join("index.theme")).unwrap().contains("16x16/places"));
+    assert_eq!(fs::read_to_string(output.join("handmade.txt")).unwrap(), "by hand");
+
+    // Dropping the folder again removes it and keeps it for restoring
+    let report = generator(false).generate().unwrap();
+    assert_eq!(report.removed, vec![Path::new("16x16/places/folder.xpm")]);
+    assert!(!output.join("16x16/places/folder.xpm").exists());
+    staged.restore_previous().unwrap();
+    assert!(output.join("16x16/places/folder.xpm").is_file());
+}
+
+#[test]
+fn restoring_waits_for_a_running_generation() {
+    let tmp = tempfile::tempdir().unwrap();
+    let output = tmp.path().join("Busy");
+    for source in ["red.svg", "green.svg"] {
+        let mut generator = ThemePackGenerator::new("Busy".to_string(), output.clone());
+        generator.add_replacement("editor".to_string(), common::source(source));
+        generator.generate().unwrap();
+    }
+    let generated = fs::read(output.join("16x16/apps/editor.png")).unwrap();
+
+    let staged = StagedOutput::new(&output);
+    let lock = staged.lock();
+    let restore = {
+        let output = output.clone();
+        std::thread::spawn(move || StagedOutput::new(&output).restore_previous())
+    };
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    assert!(!restore.is_finished());
+    assert_eq!(fs::read(output.join("16x16/apps/editor.png")).unwrap(), generated);
+
+    drop(lock);
+    restore.join().unwrap().unwrap();
+    assert_ne!(fs::read(output.join("16x16/apps/editor.png")).unwrap(), generated);
+}
@@ -0,0 +1,417 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use icon_packer_core::gtk_icon_cache::{GtkIconCache, CACHE_FILE_NAME};
+use icon_packer_core::index_theme::{IndexTheme, ICON_THEME_GROUP};
+use icon_packer_core::staging::StagedOutput;
+use icon_packer_core::theme_generator::{FileAction, ThemePackGenerator};
+use icon_packer_core::theme_layout::ThemeLayout;
+
+fn mixed_generator(output: &Path) -> ThemePackGenerator {
+    let mut generator = ThemePackGenerator::new("Golden".to_string(), output.to_path_buf());
+    generator.set_theme_comment("Generated by the tests".to_string());
+    generator.set_fallback_themes(vec!["breeze".to_string(), "hicolor".to_string()]);
+    generator.add_replacement("editor".to_string(), common::source("red.svg"));
+    generator.add_replacement("viewer".to_string(), common::source("blue-48.png"));
+    generator.set_replacement_link("viewer".to_string(), true);
+    generator.add_size_replacement("legacy".to_string(), 16, common::source("legacy.xpm"));
+    generator.add_size_replacement("folder".to_string(), 32, common::source("green.svg"));
+    generator.set_icon_category("folder".to_string(), "Places".to_string());
+    generator.add_scaled_replacement("editor".to_string(), 16, 2, common::source("green.svg"));
+    generator.set_scaled_replacement_link("editor".to_string(), 16, 2, true);
+    generator
+}
+
+#[test]
+fn golden_default_layout() {
+    let tmp = tempfile::tempdir().unwrap();
+    let output = tmp.path().join("Golden");
+
+    mixed_generator(&output).generate().unwrap();
+
+    common::assert_golden("default_layout", "tree.txt", &common::describe_tree(&output));
+    common::assert_golden("default_layout", "index.theme", &fs::read_to_string(output.join("index.theme")).unwrap());
+}
+
+#[test]
+fn golden_breeze_layout() {
+    let tmp = tempfile::tempdir().unwrap();
+    let output = tmp.path().join("Golden");
+    let mut generator = mixed_generator(&output);
+    generator.set_layout(ThemeLayout::breeze());
+
+    generator.generate().unwrap();
+
+    common::assert_golden("breeze_layout", "tree.txt", &common::describe_tree(&output));
+    common::assert_golden("breeze_layout", "index.theme", &fs::read_to_string(output.join("index.theme")).unwrap());
+}
+
+#[test]
+fn renders_and_resamples_to_exact_sizes() {
+    let tmp = tempfile::tempdir().unwrap();
+    let output = tmp.path().join("Sizes");
+    let mut generator = ThemePackGenerator::new("Sizes".to_string(), output.clone());
+    generator.add_replacement("wide".to_string(), common::source("red.svg"));
+    generator.add_replacement("photo".to_string(), common::source("blue.png"));
+    generator.add_scaled_replacement("photo".to_string(), 16, 2, common::source("red.svg"));
+
+    generator.generate().unwrap();
+
+    for (path, pixels, color) in [
+        ("16x16/apps/wide.png", 16, [255, 0, 0, 255]),
+        ("128x128/apps/wide.png", 128, [255, 0, 0, 255]),
+        ("16x16/apps/photo.png", 16, [0, 0, 255, 255]),
+        ("16x16@2/apps/photo.png", 32, [255, 0, 0, 255]),
+    ] {
+        let image = image::open(output.join(path)).unwrap().into_rgba8();
+        assert_eq!(image.dimensions(), (pixels, pixels), "{}", path);
+        assert_eq!(image.get_pixel(pixels / 2, pixels / 2).0, color, "{}", path);
+        // The sources are twice as wide as high, so the top row stays transparent
+        assert_eq!(image.get_pixel(pixels / 2, 0).0[3], 0, "{}", path);
+    }
+}
+
+#[test]
+fn cleanup_removes_only_unmodified_generated_files() {
+    let tmp = tempfile::tempdir().unwrap();
+    let output = tmp.path().join("Cleanup");
+    fs::create_dir_all(output.join("48x48/apps")).unwrap();
+    fs::write(output.join("48x48/apps/handmade.png"), "not generated").unwrap();
+    let mut generator = ThemePackGenerator::new("Cleanup".to_string(), output.clone());
+    generator.add_replacement("editor".to_string(), common::source("red.svg"));
+    generator.add_replacement("folder".to_string(), common::source("red.svg"));
+    generator.set_icon_category("folder".to_string(), "Places".to_string());
+    generator.generate().unwrap();
+    fs::write(output.join("16x16/apps/editor.png"), "edited by hand").unwrap();
+
+    let report = ThemePackGenerator::new("Cleanup".to_string(), output.clone()).generate().unwrap();
+
+    assert!(output.join("48x48/apps/handmade.png").exists());
+    assert!(output.join("16x16/apps/editor.png").exists());
+    assert!(!output.join("32x32/apps/editor.png").exists());
+    assert!(!output.join("32x32/places").exists());
+    assert_eq!(report.modified, vec![Path::new("16x16/apps/editor.png")]);
+}
+
+#[test]
+fn plan_does_not_touch_the_disk() {
+    let tmp = tempfile::tempdir().unwrap();
+    let output = tmp.path().join("Plan");
+    let mut generator = ThemePackGenerator::new("Plan".to_string(), output.clone());
+    generator.add_replacement("editor".to_string(), common::source("red.svg"));
+    generator.add_replacement("gone".to_string(), common::source("missing.png"));
+    generator.add_size_replacement("odd".to_string(), 17, common::source("red.svg"));
+
+    let plan = generator.plan().unwrap();
+
+    assert!(!output.exists());
+    assert_eq!(plan.warnings.len(), 2, "{:?}", plan.warnings);
+    assert!(plan.files.iter().any(|f| f.path == Path::new("48x48/apps/editor.png") && f.action == FileAction::Render(48)));
+    assert!(plan.files.iter().all(|f| !f.overwrite));
+}
+
+#[test]
+fn failed_generation_keeps_the_live_theme() {
+    let tmp = tempfile::tempdir().unwrap();
+    let output = tmp.path().join("Staged");
+    let mut generator = ThemePackGenerator::new("Staged".to_string(), output.clone());
+    generator.add_replacement("editor".to_string(), common::source("red.svg"));
+    generator.generate().unwrap();
+    let first = fs::read(output.join("16x16/apps/editor.png")).unwrap();
+    let mut generator = ThemePackGenerator::new("Staged".to_string(), output.clone());
+    generator.add_replacement("editor".to_string(), common::source("green.svg"));
+    generator.generate().unwrap();
+    let second = fs::read(output.join("16x16/apps/editor.png")).unwrap();
+    assert_ne!(first, second);
+
+    let mut generator = ThemePackGenerator::new("Staged".to_string(), output.clone());
+    generator.add_replacement("editor".to_string(), common::source("missing.svg"));
+    assert!(generator.generate().is_err());
+    assert_eq!(fs::read(output.join("16x16/apps/editor.png")).unwrap(), second);
+
+    StagedOutput::new(&output).restore_previous().unwrap();
+    assert_eq!(fs::read(output.join("16x16/apps/editor.png")).unwrap(), first);
+}
+
+#[test]
+fn regeneration_skips_unchanged_outputs() {
+    let tmp = tempfile::tempdir().unwrap();
+    let output = tmp.path().join("Incremental");
+    let source = tmp.path().join("icon.svg");
+    fs::copy(common::source("red.svg"), &source).unwrap();
+    let generator = |comment: &str| {
+        let mut generator = ThemePackGenerator::new("Incremental".to_string(), output.clone());
+        generator.set_theme_comment(comment.to_string());
+        generator.add_replacement("editor".to_string(), source.clone());
+        generator
+    };
+
+    let first = generator("one").generate().unwrap();
+    assert!(first.unchanged.is_empty());
+    assert!(first.index_written);
+
+    let second = generator("one").generate().unwrap();
+    assert_eq!(second.unchanged.len(), 7);
+    assert!(!second.index_written);
+
+    // Rewriting the same content only changes the mtime
+    fs::write(&source, fs::read(&source).unwrap()).unwrap();
+    assert_eq!(generator("one").generate().unwrap().unchanged.len(), 7);
+
+    fs::copy(common::source("green.svg"), &source).unwrap();
+    let changed = generator("two").generate().unwrap();
+    assert!(changed.unchanged.is_empty());
+    assert!(changed.index_written);
+}
+
+#[test]
+fn writes_a_gtk_icon_cache_of_the_output() {
+    let tmp = tempfile::tempdir().unwrap();
+    let output = tmp.path().join("Cached");
+    let mut generator = mixed_generator(&output);
+    generator.generate().unwrap();
+    // Files added by hand are listed too, like gtk-update-icon-cache would
+    fs::write(output.join("16x16/apps/handmade.svg"), "").unwrap();
+    generator.generate().unwrap();
+
+    let cache_path = output.join(CACHE_FILE_NAME);
+    let cache = GtkIconCache::load(&cache_path).unwrap();
+    let directories = cache.directories().unwrap();
+    assert_eq!(directories.len(), 9);
+    assert!(directories.contains(&"16x16@2/apps".to_string()));
+    let mut entries = cache.entries().unwrap();
+    entries.sort_by(|a, b| (&a.directory, &a.name).cmp(&(&b.directory, &b.name)));
+    let listed: Vec<String> = entries
+        .iter()
+        .flat_map(|e| e.formats.iter().map(move |f| format!("{}/{}.{}", e.directory, e.name, f.extension())))
+        .collect();
+    let on_disk: Vec<String> = common::describe_tree(&output)
+        .lines()
+        .filter(|line| line.contains('/'))
+        .map(|line| line.split(" -> ").next().unwrap().to_string())
+        .collect();
+    let mut listed_sorted = listed.clone();
+    listed_sorted.sort();
+    assert_eq!(listed_sorted, on_disk);
+    assert!(listed.contains(&"16x16/apps/handmade.svg".to_string()));
+    // GTK ignores caches older than the theme directory
+    let modified = |path: &Path| fs::metadata(path).unwrap().modified().unwrap();
+    assert!(modified(&cache_path) >= modified(&output));
+
+    generator.set_write_icon_cache(false);
+    generator.generate().unwrap();
+    assert!(!cache_path.exists());
+}
+
+#[test]
+fn regeneration_keeps_keys_added_to_index_theme() {
+    let tmp = tempfile::tempdir().unwrap();
+    let output = tmp.path().join("Edited");
+    mixed_generator(&output).generate().unwrap();
+    let index_path = output.join("index.theme");
+    let edited = fs::read_to_string(&index_path)
+        .unwrap()
+        .replace("Name=Golden\n", "Name=Golden\nName[de]=Golden DE\n")
+        .replace("Context=Places\n", "Context=Places\nX-Note=by hand\n")
+        + "FollowsColorScheme=true\n\n[X-Extra]\nKey=value\n";
+    fs::write(&index_path, edited).unwrap();
+
+    let mut generator = mixed_generator(&output);
+    generator.set_theme_comment("Changed".to_string());
+    generator.generate().unwrap();
+
+    let index = fs::read_to_string(&index_path).unwrap();
+    assert!(index.contains("Name=Golden\nName[de]=Golden DE\nComment=Changed\n"));
+    assert!(index.contains("Context=Places\nX-Note=by hand\n"));
+    assert!(index.contains("MaxSize=256\nFollowsColorScheme=true\n\n[X-Extra]\nKey=value\n"));
+}
+
+#[test]
+fn keeps_directories_of_an_existing_theme_listed() {
+    let tmp = tempfile::tempdir().unwrap();
+    let output = tmp.path().join("Existing");
+    fs::create_dir_all(output.join("custom/apps")).unwrap();
+    fs::copy(common::source("blue.png"), output.join("custom/apps/handmade.png")).unwrap();
+    fs::write(
+        output.join("index.theme"),
+        "[Icon Theme]\nName=Existing\nDirectories=custom/apps\n\n[custom/apps]\nSize=48\nContext=Applications\nType=Fixed\n",
+    )
+    .unwrap();
+    let generator = |icon: &str, category: &str| {
+        let mut generator = ThemePackGenerator::new("Existing".to_string(), output.clone());
+        generator.add_size_replacement(icon.to_string(), 16, common::source("legacy.xpm"));
+        generator.set_icon_category(icon.to_string(), category.to_string());
+        generator
+    };
+
+    generator("editor", "Applications").generate().unwrap();
+    let index = IndexTheme::load(&output.join("index.theme")).unwrap();
+    assert_eq!(index.get_list(ICON_THEME_GROUP, "Directories"), vec!["16x16/apps", "custom/apps"]);
+    assert_eq!(index.get("custom/apps", "Size"), Some("48"));
+    let cache = GtkIconCache::load(&output.join(CACHE_FILE_NAME)).unwrap();
+    assert!(cache.directories().unwrap().iter().any(|dir| dir == "custom/apps"));
+
+    // Only directories of earlier runs are dropped
+    generator("folder", "Places").generate().unwrap();
+    let index = IndexTheme::load(&output.join("index.theme")).unwrap();
+    assert_eq!(index.get_list(ICON_THEME_GROUP, "Directories"), vec!["16x16/places", "custom/apps"]);
+    assert!(index.group("16x16/apps").is_none());
+    assert!(index.group("custom/apps").is_some());
+}
+
+#[test]
+fn places_symbolic_svgs_in_symbolic_directories() {
+    let tmp = tempfile::tempdir().unwrap();
+    let output = tmp.path().join("Symbolic");
+    let mut generator = ThemePackGenerator::new("Symbolic".to_string(), output.clone());
+    generator.add_replacement("folder".to_string(), common::source("green.svg"));
+    generator.set_icon_category("folder".to_string(), "Places".to_string());
+    generator.add_replacement("folder-symbolic".to_string(), common::source("red.svg"));
+    generator.add_replacement("go-up-symbolic".to_string(), common::source("red.svg"));
+    generator.set_icon_category("go-up-symbolic".to_string(), "Actions".to_string());
+
+    let plan = generator.plan().unwrap();
+    let symbolic: Vec<String> = plan
+        .files
+        .iter()
+        .map(|file| file.path.to_string_lossy().into_owned())
+        .filter(|path| path.contains("-symbolic"))
+        .collect();
+    // Kept as SVGs in the context of their full color icon, never rendered
+    assert_eq!(symbolic, vec!["symbolic/actions/go-up-symbolic.svg", "symbolic/places/folder-symbolic.svg"]);
+
+    generator.generate().unwrap();
+    let index = fs::read_to_string(output.join("index.theme")).unwrap();
+    assert!(index.contains("[symbolic/places]\nSize=16\nContext=Places\nType=Scaled\nMinSize=8\nMaxSize=512\n"));
+
+    let mut breeze = ThemePackGenerator::new("Breeze".to_string(), tmp.path().join("Breeze"));
+    breeze.set_layout(ThemeLayout::breeze());
+    breeze.add_replacement("go-up-symbolic".to_string(), common::source("red.svg"));
+    let paths: Vec<_> = breeze.plan().unwrap().files.into_iter().map(|file| file.path).collect();
+    assert_eq!(paths, vec![Path::new("apps/symbolic/go-up-symbolic.svg")]);
+
+    // Sized replacements end up in the symbolic directory too, raster ones are refused
+    let mut sized = ThemePackGenerator::new("Sized".to_string(), tmp.path().join("Sized"));
+    sized.add_size_replacement("edit-symbolic".to_string(), 24, common::source("green.svg"));
+    sized.add_scaled_replacement("edit-symbolic".to_string(), 16, 2, common::source("red.svg"));
+    sized.add_replacement("raster-symbolic".to_string(), common::source("blue.png"));
+    sized.add_size_replacement("raster-symbolic".to_string(), 16, common::source("legacy.xpm"));
+    let plan = sized.plan().unwrap();
+    assert_eq!(plan.files.len(), 1, "{:?}", plan.files);
+    assert_eq!(plan.files[0].path, Path::new("symbolic/apps/edit-symbolic.svg"));
+    assert_eq!(plan.files[0].source, common::source("green.svg"));
+    assert_eq!(plan.warnings.len(), 3, "{:?}", plan.warnings);
+    assert!(plan.warnings.iter().any(|warning| warning.contains("ignoring") && warning.contains("red.svg")));
+    assert_eq!(plan.warnings.iter().filter(|warning| warning.contains("needs an SVG")).count(), 2);
+}
+
+#[test]
+fn aliases_are_relative_links_to_the_icon() {
+    let tmp = tempfile::tempdir().unwrap();
+    let output = tmp.path().join("Aliased");
+    let generator = |aliases: &[&str]| {
+        let mut generator = ThemePackGenerator::new("Aliased".to_string(), output.clone());
+        generator.add_replacement("firefox".to_string(), common::source("red.svg"));
+        for alias in aliases {
+            generator.add_alias(alias.to_string(), "firefox".to_string());
+        }
+        generator
+    };
+
+    generator(&["org.mozilla.firefox", "firefox-esr"]).generate().unwrap();
+    assert_eq!(
+        fs::read_link(output.join("scalable/apps/org.mozilla.firefox.svg")).unwrap(),
+        Path::new("firefox.svg")
+    );
+    assert_eq!(fs::read_link(output.join("48x48/apps/firefox-esr.png")).unwrap(), Path::new("firefox.png"));
+    // Still valid once the theme is moved somewhere else
+    let moved = tmp.path().join("Moved");
+    fs::rename(&output, &moved).unwrap();
+    assert!(moved.join("16x16/apps/firefox-esr.png").is_file());
+    fs::rename(&moved, &output).unwrap();
+
+    let report = generator(&["firefox-esr"]).generate().unwrap();
+    assert!(report.removed.contains(&Path::new("48x48/apps/org.mozilla.firefox.png").to_path_buf()));
+    assert!(report.unchanged.contains(&Path::new("48x48/apps/firefox-esr.png").to_path_buf()));
+
+    let mut missing = ThemePackGenerator::new("Aliased".to_string(), output.clone());
+    missing.add_alias("chromium-browser".to_string(), "chromium".to_string());
+    assert_eq!(
+        missing.plan().unwrap().warnings,
+        vec!["Alias chromium-browser refers to chromium, which has no replacement"]
+    );
+}
+
+#[test]
+fn linked_replacements_point_at_a_copy_inside_the_theme() {
+    let tmp = tempfile::tempdir().unwrap();
+    let output = tmp.path().join("Linked");
+    let generator = |allow_external: bool| {
+        let mut generator = ThemePackGenerator::new("Linked".to_string(), output.clone());
+        for icon in ["viewer", "xviewer"] {
+            generator.add_replacement(icon.to_string(), common::source("blue-48.png"));
+            generator.set_replacement_link(icon.to_string(), true);
+        }
+        generator.add_scaled_replacement("viewer".to_string(), 16, 3, common::source("blue-48.png"));
+        generator.set_scaled_replacement_link("viewer".to_string(), 16, 3, true);
+        generator.set_allow_external_links(allow_external);
+        generator
+    };
+
+    let plan = generator(false).plan().unwrap();
+    let action = |path: &str| plan.files.iter().find(|f| f.path == Path::new(path)).unwrap().action;
+    // Only the directory of the PNG's own size gets it as it is
+    assert_eq!(action("16x16/apps/viewer.png"), FileAction::Resample(16));
+    assert_eq!(action("128x128/apps/viewer.png"), FileAction::Resample(128));
+    assert_eq!(action("48x48/apps/viewer.png"), FileAction::Copy);
+    assert!(plan.warnings.is_empty(), "{:?}", plan.warnings);
+
+    generator(false).generate().unwrap();
+    assert_eq!(fs::read_link(output.join("48x48/apps/xviewer.png")).unwrap(), Path::new("viewer.png"));
+    assert_eq!(
+        fs::read_link(output.join("16x16@3/apps/viewer.png")).unwrap(),
+        Path::new("../../48x48/apps/viewer.png")
+    );
+    let moved = tmp.path().join("Moved");
+    fs::rename(&output, &moved).unwrap();
+    assert!(moved.join("16x16@3/apps/viewer.png").is_file());
+    fs::rename(&moved, &output).unwrap();
+
+    let plan = generator(true).plan().unwrap();
+    let external = plan.files.iter().find(|f| f.path == Path::new("48x48/apps/viewer.png")).unwrap();
+    assert_eq!(external.action, FileAction::Link);
+    assert_eq!(external.source, common::source("blue-48.png"));
+    assert_eq!(plan.warnings.len(), 3, "{:?}", plan.warnings);
+    assert!(plan.warnings[0].contains("outside the theme"), "{}", plan.warnings[0]);
+
+    let mut odd = ThemePackGenerator::new("Linked".to_string(), output.clone());
+    odd.add_replacement("viewer".to_string(), common::source("blue.png"));
+    odd.set_replacement_link("viewer".to_string(), true);
+    let plan = odd.plan().unwrap();
+    assert_eq!(plan.warnings, vec!["Linked replacement for viewer fits no directory size, resampling it"]);
+    assert!(plan.files.iter().all(|f| matches!(f.action, FileAction::Resample(_))));
+}
+
+#[test]
+fn resampling_keeps_transparent_colors_out_of_the_edges() {
+    let tmp = tempfile::tempdir().unwrap();
+    // Opaque blue stripes on fully transparent red
+    let source = tmp.path().join("stripes.png");
+    image::RgbaImage::from_fn(64, 64, |x, _| {
+        if (x / 4) % 2 == 0 {
+            image::Rgba([0, 0, 255, 255])
+        } else {
+            image::Rgba([255, 0, 0, 0])
+        }
+    })
+    .save(&source)
+    .unwrap();
+
+    let image = icon_packer_core::rasterizer::resample_image(&source, 24).unwrap();
+
+    for pixel in image.pixels().filter(|pixel| pixel.0[3] > 0) {
+        assert!(pixel.0[0] < 8, "{:?}", pixel.0);
+    }
+}
@@ -5,22 +5,53 @@ import Qt.labs.platform 1.1 as Platform
 import org.kde.kirigami 2.19 as Kirigami
 import ProjectManager 1.0
 
-import QtQuick 2.15
-import QtQuick.Controls 2.15
-import QtQuick.Layouts 1.15
-import Qt.labs.platform 1.1 as Platform
-import org.kde.kirigami 2.19 as Kirigami
-import ProjectManager 1.0
-
 Dialog {
     id: dialog
     title: "Generate Icon Theme"
     modal: true
-    width: 500
-    height: 250
+    width: 700
+    height: previewing ? 600 : 250
     
     property ProjectManager projectManager
     property var onExport: null
+    property bool previewing: false
+    property var plan: ({})
+    property bool exporting: false
+
+    function planText() {
+        var sections = []
+        if (plan.warnings) {
+            sections.push("Warnings:\n" + plan.warnings)
+        }
+        if (plan.files) {
+            sections.push("Files:\n" + plan.files)
+        }
+        if (plan.deletions) {
+            sections.push("Deletions:\n" + plan.deletions)
+        }
+        if (plan.directories) {
+            sections.push("index.theme directories:\n" + plan.directories)
+        }
+        return sections.join("\n\n")
+    }
+
+    onClosed: previewing = false
+    
+    // Exports run in the background, the dialog stays open until they are done
+    Connections {
+        target: projectManager
+        enabled: dialog.exporting
+        function onExport_finished(outputPath) {
+            dialog.exporting = false
+            if (dialog.onExport) {
+                dialog.onExport(outputPath)
+            }
+            dialog.close()
+        }
+        function onGeneration_failed(message) {
+            dialog.exporting = false
+        }
+    }
     
     ColumnLayout {
         anchors.fill: parent
@@ -34,6 +65,7 @@ Dialog {
             id: themeNameField
             placeholderText: "My Icon Theme"
             text: projectManager ? projectManager.projectName : ""
+            enabled: !dialog.previewing
             Layout.fillWidth: true
         }
         
@@ -47,31 +79,71 @@ Dialog {
             TextField {
                 id: outputPathField
                 placeholderText: "Select output directory"
+                enabled: !dialog.previewing
                 Layout.fillWidth: true
             }
             
             Button {
                 text: "Browse"
+                enabled: !dialog.previewing
                 onClicked: folderDialog.open()
             }
         }
+
+        Label {
+            visible: dialog.previewing
+            text: dialog.plan.error ? "Cannot generate: " + dialog.plan.error : (dialog.plan.summary || "")
+            wrapMode: Text.WordWrap
+            font.bold: true
+            Layout.fillWidth: true
+        }
+
+        ScrollView {
+            visible: dialog.previewing
+            Layout.fillWidth: true
+            Layout.fillHeight: true
+
+            TextArea {
+                readOnly: true
+                wrapMode: TextEdit.NoWrap
+                font.family: "monospace"
+                text: dialog.previewing ? dialog.planText() : ""
+            }
+        }
         
         RowLayout {
             Layout.fillWidth: true
             
+            Button {
+                text: "Preview"
+                icon.name: "document-preview"
+                visible: !dialog.previewing
+                Layout.fillWidth: true
+                onClicked: {
+                    if (projectManager && themeNameField.text && outputPathField.text) {
+                        dialog.plan = projectManager.plan_theme(themeNameField.text, outputPathField.text)
+                        dialog.previewing = true
+                    }
+                }
+            }
+
+            Button {
+                text: "Back"
+                icon.name: "go-previous"
+                visible: dialog.previewing
+                Layout.fillWidth: true
+                onClicked: dialog.previewing = false
+            }
+            
             Button {
                 text: "Generate"
                 icon.name: "document-export"
+                visible: dialog.previewing
+                enabled: !dialog.plan.error && !dialog.exporting
                 Layout.fillWidth: true
                 onClicked: {
                     if (projectManager && themeNameField.text && outputPathField.text) {
-                        var success = projectManager.generate_theme(themeNameField.text, outputPathField.text)
-                        if (success) {
-                            if (dialog.onExport) {
-                                dialog.onExport(outputPathField.text)
-                            }
-                            dialog.close()
-                        }
+                        dialog.exporting = projectManager.generate_theme(themeNameField.text, outputPathField.text)
                     }
                 }
             }
@@ -94,4 +166,3 @@ Dialog {
         }
     }
 }
-
@@ -5,6 +5,7 @@ import Qt.labs.platform 1.1 as Platform
 import org.kde.kirigami 2.19 as Kirigami
 import IconModel 1.0
 import ProjectManager 1.0
+import ThemeManager 1.0
 
 Dialog {
     id: dialog
@@ -16,11 +17,29 @@ Dialog {
     property string iconName: ""
     property IconModel iconModel: null
     property ProjectManager projectManager: null
+    property ThemeManager themeManager: null
+    property int overrideScale: 1
+    property string layoutSizes: ""
+    property string aliases: ""
+    // The theme the overrides replace icons of, the size previews show its icon
+    property string fallbackTheme: ""
+    
+    onProjectManagerChanged: {
+        layoutSizes = projectManager ? projectManager.get_layout_sizes() : ""
+        fallbackTheme = projectManager ? projectManager.get_fallback_themes().split(",")[0] : ""
+    }
+    
+    onIconNameChanged: {
+        aliases = projectManager && iconName ? projectManager.get_aliases(iconName) : ""
+    }
     
     Connections {
         target: projectManager
         enabled: projectManager !== null
         function onCurrent_project_changed() {
+            dialog.layoutSizes = dialog.projectManager.get_layout_sizes()
+            dialog.fallbackTheme = dialog.projectManager.get_fallback_themes().split(",")[0]
+            dialog.aliases = dialog.iconName ? dialog.projectManager.get_aliases(dialog.iconName) : ""
             Qt.callLater(function() {
                 baseSvgField.text = ""
                 Qt.callLater(function() {
@@ -73,6 +92,17 @@ Dialog {
                 opacity: 0.8
             }
             
+            Label {
+                property string symbolicName: dialog.iconModel && dialog.iconName ? dialog.iconModel.get_symbolic_name(dialog.iconName) : ""
+                text: dialog.iconName.endsWith("-symbolic")
+                    ? "Symbolic icon, a base SVG is kept as it is in symbolic/ so the desktop can recolor it"
+                    : "Symbolic version: " + symbolicName
+                visible: dialog.iconName.endsWith("-symbolic") || symbolicName !== ""
+                Layout.fillWidth: true
+                wrapMode: Text.WordWrap
+                opacity: 0.8
+            }
+            
             RowLayout {
                 Layout.fillWidth: true
                 spacing: Kirigami.Units.smallSpacing
@@ -137,6 +167,67 @@ Dialog {
                 Layout.preferredHeight: Kirigami.Units.mediumSpacing
             }
             
+            Label {
+                text: "Aliases"
+                font.bold: true
+                font.pointSize: 13
+                Layout.fillWidth: true
+            }
+            
+            Label {
+                text: "Other names apps use for this icon, they link to its files instead of copying them"
+                Layout.fillWidth: true
+                wrapMode: Text.WordWrap
+                opacity: 0.8
+            }
+            
+            Flow {
+                Layout.fillWidth: true
+                spacing: Kirigami.Units.smallSpacing
+                visible: dialog.aliases !== ""
+                
+                Repeater {
+                    model: dialog.aliases !== "" ? dialog.aliases.split(",") : []
+                    
+                    Button {
+                        text: modelData
+                        icon.name: "list-remove"
+                        display: Button.TextBesideIcon
+                        ToolTip.visible: hovered
+                        ToolTip.text: "Remove alias"
+                        onClicked: dialog.projectManager.remove_alias(modelData)
+                    }
+                }
+            }
+            
+            RowLayout {
+                Layout.fillWidth: true
+                spacing: Kirigami.Units.smallSpacing
+                
+                TextField {
+                    id: aliasField
+                    Layout.fillWidth: true
+                    placeholderText: "e.g. org.example.App"
+                    onAccepted: addAliasButton.clicked()
+                }
+                
+                Button {
+                    id: addAliasButton
+                    text: "Add Alias"
+                    icon.name: "list-add"
+                    enabled: dialog.projectManager !== null && aliasField.text.trim() !== ""
+                    onClicked: {
+                        if (dialog.projectManager.add_alias(dialog.iconName, aliasField.text)) {
+                            aliasField.text = ""
+                        }
+                    }
+                }
+            }
+            
+            Item {
+                Layout.preferredHeight: Kirigami.Units.mediumSpacing
+            }
+            
             Label {
                 text: "Individual Size Overrides"
                 font.bold: true
@@ -151,6 +242,21 @@ Dialog {
                 opacity: 0.8
             }
             
+            RowLayout {
+                Layout.fillWidth: true
+                spacing: Kirigami.Units.smallSpacing
+                
+                Label {
+                    text: "Scale:"
+                }
+                
+                ComboBox {
+                    id: scaleCombo
+                    model: ["1x", "2x (HiDPI)", "3x (HiDPI)"]
+                    onCurrentIndexChanged: dialog.overrideScale = currentIndex + 1
+                }
+            }
+            
             GridLayout {
                 columns: 3
                 Layout.fillWidth: true
@@ -158,14 +264,12 @@ Dialog {
                 columnSpacing: Kirigami.Units.mediumSpacing
                 
                 Repeater {
-                    model: [
-                        {size: 16, label: "16px"},
-                        {size: 24, label: "24px"},
-                        {size: 32, label: "32px"},
-                        {size: 48, label: "48px"},
-                        {size: 64, label: "64px"},
-                        {size: 128, label: "128px"}
-                    ]
+                    model: {
+                        if (!dialog.layoutSizes) return []
+                        return dialog.layoutSizes.split(",").map(function(size) {
+                            return {size: parseInt(size), label: size + "px"}
+                        })
+                    }
                     
                     ColumnLayout {
                         Layout.fillWidth: true
@@ -177,6 +281,23 @@ Dialog {
                             Layout.alignment: Qt.AlignHCenter
                         }
                         
+                        Image {
+                            // What the desktop shows at this size and the selected scale today
+                            source: {
+                                if (!dialog.themeManager || !dialog.iconName || !dialog.fallbackTheme) return ""
+                                var path = dialog.themeManager.get_icon_path(dialog.fallbackTheme, dialog.iconName, modelData.size, dialog.overrideScale)
+                                return path ? "file://" + path : ""
+                            }
+                            sourceSize.width: modelData.size * dialog.overrideScale
+                            sourceSize.height: modelData.size * dialog.overrideScale
+                            Layout.preferredWidth: Kirigami.Units.iconSizes.medium
+                            Layout.preferredHeight: Kirigami.Units.iconSizes.medium
+                            Layout.alignment: Qt.AlignHCenter
+                            fillMode: Image.PreserveAspectFit
+                            visible: source != ""
+                            asynchronous: true
+                        }
+                        
                         TextField {
                             id: sizeField
                             Layout.fillWidth: true
@@ -184,7 +305,9 @@ Dialog {
                             readOnly: true
                             text: {
                                 if (!dialog.projectManager || !dialog.iconName) return ""
-                                var sizeMap = dialog.projectManager.get_size_replacements(dialog.iconName)
+                                var sizeMap = dialog.overrideScale === 1
+                                    ? dialog.projectManager.get_size_replacements(dialog.iconName)
+                                    : dialog.projectManager.get_scaled_replacements(dialog.iconName, dialog.overrideScale)
                                 if (sizeMap && sizeMap[modelData.size.toString()]) {
                                     var path = sizeMap[modelData.size.toString()]
                                     var parts = path.split("/")
@@ -203,6 +326,7 @@ Dialog {
                                 Layout.fillWidth: true
                                 onClicked: {
                                     sizeFileDialog.size = modelData.size
+                                    sizeFileDialog.scale = dialog.overrideScale
                                     sizeFileDialog.isLink = false
                                     sizeFileDialog.open()
                                 }
@@ -215,6 +339,7 @@ Dialog {
                                 Layout.fillWidth: true
                                 onClicked: {
                                     sizeFileDialog.size = modelData.size
+                                    sizeFileDialog.scale = dialog.overrideScale
                                     sizeFileDialog.isLink = true
                                     sizeFileDialog.open()
                                 }
@@ -226,7 +351,11 @@ Dialog {
                                 enabled: sizeField.text !== ""
                                 onClicked: {
                                     if (dialog.projectManager && dialog.iconName) {
-                                        dialog.projectManager.add_size_replacement(dialog.iconName, modelData.size, "")
+                                        if (dialog.overrideScale === 1) {
+                                            dialog.projectManager.add_size_replacement(dialog.iconName, modelData.size, "")
+                                        } else {
+                                            dialog.projectManager.add_scaled_replacement(dialog.iconName, modelData.size, dialog.overrideScale, "")
+                                        }
                                     }
                                 }
                             }
@@ -272,15 +401,23 @@ Dialog {
     Platform.FileDialog {
         id: sizeFileDialog
         property int size: 16
+        property int scale: 1
         property bool isLink: false
-        title: (isLink ? "Link" : "Select") + " Icon File for " + sizeFileDialog.size + "px"
+        title: (isLink ? "Link" : "Select") + " Icon File for " + sizeFileDialog.size + "px" + (scale > 1 ? " @" + scale + "x" : "")
         nameFilters: ["SVG Files (*.svg)", "PNG Files (*.png)", "All Files (*.*)"]
         onAccepted: {
             if (file && dialog.projectManager && dialog.iconName) {
                 var filePath = file.toString().replace("file://", "")
-                dialog.projectManager.add_size_replacement(dialog.iconName, sizeFileDialog.size, filePath)
-                if (isLink) {
-                    dialog.projectManager.set_size_replacement_link(dialog.iconName, sizeFileDialog.size, true)
+                if (sizeFileDialog.scale === 1) {
+                    dialog.projectManager.add_size_replacement(dialog.iconName, sizeFileDialog.size, filePath)
+                    if (isLink) {
+                        dialog.projectManager.set_size_replacement_link(dialog.iconName, sizeFileDialog.size, true)
+                    }
+                } else {
+                    dialog.projectManager.add_scaled_replacement(dialog.iconName, sizeFileDialog.size, sizeFileDialog.scale, filePath)
+                    if (isLink) {
+                        dialog.projectManager.set_scaled_replacement_link(dialog.iconName, sizeFileDialog.size, sizeFileDialog.scale, true)
+                    }
                 }
                 if (dialog.iconModel) {
                     var category = dialog.iconModel.get_icon_category(dialog.iconName)
@@ -288,7 +425,7 @@ Dialog {
                 } else {
                     dialog.projectManager.set_icon_category(dialog.iconName, "Applications")
                 }
-                if (dialog.iconModel && sizeFileDialog.size === 48) {
+                if (dialog.iconModel && sizeFileDialog.size === 48 && sizeFileDialog.scale === 1) {
                     dialog.iconModel.set_replacement(dialog.iconName, filePath)
                 }
                 Qt.callLater(function() {
@@ -28,6 +28,13 @@ Kirigami.ApplicationWindow {
                 shortcut: "Ctrl+O"
                 onTriggered: openProjectDialog.open()
             }
+            Action {
+                text: "Export Theme…"
+                icon.name: "document-export"
+                shortcut: "Ctrl+E"
+                enabled: projectManager !== null && projectManager.hasProject === true
+                onTriggered: openGenerateDialog()
+            }
             MenuSeparator {}
             Action {
                 text: "Quit"
@@ -46,12 +53,39 @@ Kirigami.ApplicationWindow {
                 enabled: projectManager !== null && projectManager.hasProject === true
                 onTriggered: openProjectSettingsDialog()
             }
+            Action {
+                text: "Restore Previous Generation"
+                icon.name: "edit-undo"
+                enabled: projectManager !== null && projectManager.hasProject === true
+                onTriggered: {
+                    if (projectManager.restore_previous_generation()) {
+                        var replacements = projectManager.get_replacements()
+                        for (var iconName in replacements) {
+                            var path = replacements[iconName]
+                            if (path && path !== "" && path !== "null" && path !== "undefined") {
+                                iconModel.set_replacement(iconName, path)
+                            }
+                        }
+                    }
+                }
+            }
             Action {
                 text: "Load Icons"
                 icon.name: "view-refresh"
                 enabled: projectManager !== null && projectManager.hasProject === true
                 onTriggered: iconModel.load_catalog()
             }
+            Action {
+                text: "Refresh Icon Catalog"
+                icon.name: "view-refresh"
+                shortcut: "F5"
+                onTriggered: iconModel.refresh_catalog()
+            }
+            Action {
+                text: "Icon Search Paths…"
+                icon.name: "folder-open"
+                onTriggered: openSearchPathsDialog()
+            }
         }
         Menu {
             title: "Help"
@@ -67,6 +101,26 @@ Kirigami.ApplicationWindow {
         id: projectManager
     }
 
+    Connections {
+        target: projectManager
+        function onGeneration_progress(done, total) {
+            generationProgress.value = total > 0 ? done / total : 1
+        }
+        function onGeneration_failed(message) {
+            showPassiveNotification("Theme generation failed: " + message, "long")
+        }
+        function onCurrent_project_changed() {
+            iconModel.set_themes(projectManager.has_project ? projectManager.get_fallback_themes() : "")
+        }
+    }
+
+    footer: ProgressBar {
+        id: generationProgress
+        visible: projectManager.generating
+        from: 0
+        to: 1
+    }
+
     IconModel {
         id: iconModel
         Component.onCompleted: {
@@ -320,6 +374,55 @@ Kirigami.ApplicationWindow {
         }
     }
 
+    property var generateDialogInstance: null
+
+    function openGenerateDialog() {
+        if (!generateDialogInstance) {
+            generateDialogInstance = generateDialogComponent.createObject(root)
+        }
+        if (generateDialogInstance.item) {
+            generateDialogInstance.item.open()
+        }
+    }
+
+    Component {
+        id: generateDialogComponent
+        Loader {
+            source: "qrc:///generate_dialog.qml"
+            asynchronous: false
+            onItemChanged: {
+                if (item) {
+                    item.projectManager = root.projectManager
+                }
+            }
+        }
+    }
+
+    property var searchPathsDialogInstance: null
+
+    function openSearchPathsDialog() {
+        if (!searchPathsDialogInstance) {
+            searchPathsDialogInstance = searchPathsDialogComponent.createObject(root)
+        }
+        if (searchPathsDialogInstance.item) {
+            searchPathsDialogInstance.item.open()
+        }
+    }
+
+    Component {
+        id: searchPathsDialogComponent
+        Loader {
+            source: "qrc:///search_paths_dialog.qml"
+            asynchronous: false
+            onItemChanged: {
+                if (item) {
+                    item.themeManager = themeManager
+                    item.iconModel = iconModel
+                }
+            }
+        }
+    }
+
     property var aboutSheet: null
 
     Component.onCompleted: {
@@ -64,7 +64,7 @@ Kirigami.ScrollablePage {
                     ComboBox {
                         id: categoryFilter
                         Layout.fillWidth: true
-                        model: ["All Categories", "Applications", "Mimetypes", "Actions", "Places", "Devices", "Status"]
+                        model: ["All Categories", "Applications", "Mimetypes", "Actions", "Places", "Devices", "Status", "Symbolic", "Unthemed"]
                         onCurrentTextChanged: {
                             if (iconModel) {
                                 iconModel.set_category_filter(currentText)
@@ -127,8 +127,10 @@ Kirigami.ScrollablePage {
                         anchors.fill: parent
                         anchors.margins: 4
                         color: iconMouseArea.containsMouse ? Kirigami.Theme.hoverColor : Kirigami.Theme.backgroundColor
-                        border.color: model.replacementPath ? Kirigami.Theme.positiveTextColor : Kirigami.Theme.disabledTextColor
-                        border.width: model.replacementPath ? 2 : 1
+                        border.color: model.coverage === "replaced" ? Kirigami.Theme.positiveTextColor
+                            : model.coverage === "generic" ? Kirigami.Theme.neutralTextColor
+                            : Kirigami.Theme.disabledTextColor
+                        border.width: model.coverage === "inherited" ? 1 : 2
                         radius: 8
                         
                         MouseArea {
@@ -230,6 +232,42 @@ Kirigami.ScrollablePage {
                                         hoverEnabled: true
                                     }
                                 }
+                                
+                                Rectangle {
+                                    anchors.top: parent.top
+                                    anchors.right: parent.right
+                                    anchors.topMargin: -4
+                                    anchors.rightMargin: -4
+                                    width: 16
+                                    height: 16
+                                    radius: 8
+                                    color: Kirigami.Theme.neutralTextColor
+                                    visible: model.coverage === "generic"
+                                    border.color: Kirigami.Theme.backgroundColor
+                                    border.width: 2
+                                    
+                                    Kirigami.Icon {
+                                        anchors.centerIn: parent
+                                        source: "go-up"
+                                        width: 10
+                                        height: 10
+                                        color: "white"
+                                        fallback: ""
+                                    }
+                                    
+                                    ToolTip {
+                                        visible: parent.visible && genericIndicatorMouseArea.containsMouse
+                                        text: "Falls back to the replaced " + model.genericName
+                                        delay: 500
+                                        timeout: 3000
+                                    }
+                                    
+                                    MouseArea {
+                                        id: genericIndicatorMouseArea
+                                        anchors.fill: parent
+                                        hoverEnabled: true
+                                    }
+                                }
                             }
                             
                             Label {
@@ -77,6 +77,67 @@ Dialog {
             Layout.preferredHeight: Kirigami.Units.mediumSpacing
         }
         
+        Label {
+            text: "Directory Layout"
+            font.bold: true
+            Layout.fillWidth: true
+        }
+        
+        ComboBox {
+            id: layoutCombo
+            Layout.fillWidth: true
+            textRole: "label"
+            model: [
+                {preset: "default", label: "Default (16x16/apps, 16–128px)"},
+                {preset: "breeze", label: "Breeze (apps/48, 16–256px)"},
+                {preset: "custom", label: "Custom (edited in project file)"}
+            ]
+            onActivated: {
+                var preset = model[currentIndex].preset
+                if (projectManager && !updatingFields && preset !== "custom") {
+                    projectManager.set_layout_preset(preset)
+                }
+            }
+        }
+        
+        CheckBox {
+            id: iconCacheCheck
+            text: "Write GTK icon cache (icon-theme.cache)"
+            onToggled: {
+                if (projectManager && !updatingFields) {
+                    projectManager.set_write_icon_cache(checked)
+                }
+            }
+        }
+        
+        CheckBox {
+            id: externalLinksCheck
+            text: "Allow links to files outside the theme"
+            onToggled: {
+                if (projectManager && !updatingFields) {
+                    projectManager.set_allow_external_links(checked)
+                }
+            }
+            ToolTip.visible: hovered
+            ToolTip.text: "Linked replacements point at the original file instead of a copy inside the theme. The theme then only works on this machine."
+        }
+        
+        CheckBox {
+            id: vendorAssetsCheck
+            text: "Keep replacement files in the project (assets folder)"
+            onToggled: {
+                if (projectManager && !updatingFields) {
+                    projectManager.set_vendor_assets(checked)
+                }
+            }
+            ToolTip.visible: hovered
+            ToolTip.text: "Replacement files are copied into the project, so the project folder can be committed to git and shared. Files no replacement uses any more are removed."
+        }
+        
+        Item {
+            Layout.preferredHeight: Kirigami.Units.mediumSpacing
+        }
+        
         Label {
             text: "Fallback Themes"
             font.bold: true
@@ -155,6 +216,15 @@ Dialog {
         updatingFields = true
         themeNameField.text = projectManager.get_theme_name()
         themeCommentField.text = projectManager.get_theme_comment()
+        iconCacheCheck.checked = projectManager.get_write_icon_cache()
+        externalLinksCheck.checked = projectManager.get_allow_external_links()
+        vendorAssetsCheck.checked = projectManager.get_vendor_assets()
+        var preset = projectManager.get_layout_preset()
+        for (var i = 0; i < layoutCombo.model.length; i++) {
+            if (layoutCombo.model[i].preset === preset) {
+                layoutCombo.currentIndex = i
+            }
+        }
         updatingFields = false
     }
     
@@ -0,0 +1,77 @@
+import QtQuick 2.15
+import QtQuick.Controls 2.15
+import QtQuick.Layouts 1.15
+import org.kde.kirigami 2.19 as Kirigami
+import IconModel 1.0
+import ThemeManager 1.0
+
+Dialog {
+    id: dialog
+    title: "Icon Search Paths"
+    modal: true
+    width: 600
+    height: 500
+
+    property ThemeManager themeManager
+    property IconModel iconModel
+
+    onOpened: {
+        if (themeManager) {
+            extraPathsArea.text = themeManager.get_extra_search_paths()
+            searchPathsLabel.text = themeManager.get_search_paths()
+        }
+    }
+
+    ColumnLayout {
+        anchors.fill: parent
+        spacing: Kirigami.Units.largeSpacing
+
+        Label {
+            text: "Additional folders with icon themes, one per line"
+            Layout.fillWidth: true
+        }
+
+        ScrollView {
+            Layout.fillWidth: true
+            Layout.fillHeight: true
+
+            TextArea {
+                id: extraPathsArea
+                placeholderText: "e.g., /mnt/team-assets/icons"
+            }
+        }
+
+        Label {
+            text: "Searched folders, in order"
+            Layout.fillWidth: true
+            font.bold: true
+        }
+
+        Label {
+            id: searchPathsLabel
+            Layout.fillWidth: true
+            font.pointSize: 9
+            color: Kirigami.Theme.disabledTextColor
+            wrapMode: Text.WrapAnywhere
+        }
+
+        RowLayout {
+            Layout.fillWidth: true
+            Item { Layout.fillWidth: true }
+            Button {
+                text: "Cancel"
+                onClicked: dialog.close()
+            }
+            Button {
+                text: "Save"
+                highlighted: true
+                onClicked: {
+                    if (themeManager.set_extra_search_paths(extraPathsArea.text)) {
+                        iconModel.refresh_catalog()
+                        dialog.close()
+                    }
+                }
+            }
+        }
+    }
+}
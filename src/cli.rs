@@ -0,0 +1,337 @@
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use clap::{CommandFactory, Parser, Subcommand};
+
+use icon_packer_core::assets;
+use icon_packer_core::project::{IconProject, PROJECT_FILE_NAME};
+use icon_packer_core::theme_generator::ThemePackGenerator;
+use icon_packer_core::theme_layout::ThemeLayout;
+
+// Headless access to projects for scripts and packaging. Commands that edit a project
+// only update its project file, `generate` builds the theme.
+#[derive(Parser)]
+#[command(name = "icon-packer", version, about = "Build freedesktop icon themes")]
+struct Cli {
+    #[arg(short, long, global = true)]
+    verbose: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create a project in <PARENT>/<NAME>
+    New {
+        name: String,
+        parent: PathBuf,
+        #[arg(long)]
+        comment: Option<String>,
+        /// Comma-separated themes to inherit from
+        #[arg(long, value_delimiter = ',')]
+        inherits: Option<Vec<String>>,
+        /// Directory layout preset: default or breeze
+        #[arg(long)]
+        layout: Option<String>,
+    },
+    /// Replace an icon, for all sizes or only one
+    Add {
+        project: PathBuf,
+        icon: String,
+        file: PathBuf,
+        #[arg(long)]
+        size: Option<u32>,
+        #[arg(long, default_value_t = 1, requires = "size")]
+        scale: u32,
+        /// Symlink the file instead of copying it
+        #[arg(long)]
+        link: bool,
+    },
+    /// Remove a replacement
+    Remove {
+        project: PathBuf,
+        icon: String,
+        #[arg(long)]
+        size: Option<u32>,
+        #[arg(long, default_value_t = 1, requires = "size")]
+        scale: u32,
+    },
+    /// Symlink a replacement instead of copying it
+    Link {
+        project: PathBuf,
+        icon: String,
+        #[arg(long)]
+        size: Option<u32>,
+        #[arg(long, default_value_t = 1, requires = "size")]
+        scale: u32,
+        /// Copy the file again
+        #[arg(long)]
+        off: bool,
+    },
+    /// Serve a replaced icon under another name as well, through relative symlinks
+    Alias {
+        project: PathBuf,
+        icon: String,
+        alias: String,
+        /// Remove the alias instead
+        #[arg(long)]
+        remove: bool,
+    },
+    /// Keep replacement files in the project's assets folder, so the project can be shared
+    Vendor {
+        project: PathBuf,
+        /// Stop importing new files, the ones already imported stay
+        #[arg(long)]
+        off: bool,
+    },
+    /// Set the category an icon is placed under
+    SetCategory {
+        project: PathBuf,
+        icon: String,
+        category: String,
+    },
+    /// Build the theme into the project directory or another output directory
+    Generate {
+        project: PathBuf,
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Only print what would be done
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// List the replaced icons
+    List {
+        project: PathBuf,
+    },
+    /// Check the project for missing files and problems, exits with 1 if there are any
+    Validate {
+        project: PathBuf,
+    },
+}
+
+// Whether the arguments ask for the command line interface rather than the GUI. Global
+// flags may come before the subcommand, anything else is left to Qt.
+pub fn is_cli_invocation(args: &[OsString]) -> bool {
+    let command = Cli::command();
+    let is_global_flag = |arg: &str| {
+        command.get_arguments().filter(|a| a.is_global_set()).any(|a| {
+            a.get_short().is_some_and(|short| arg == format!("-{}", short))
+                || a.get_long().is_some_and(|long| arg == format!("--{}", long))
+        })
+    };
+    let Some(first) = args.iter().skip(1).filter_map(|a| a.to_str()).find(|a| !is_global_flag(a)) else {
+        return false;
+    };
+    matches!(first, "-h" | "--help" | "-V" | "--version" | "help")
+        || command.get_subcommands().any(|c| c.get_name() == first)
+}
+
+// Runs the command line interface and returns the exit code
+pub fn run(args: Vec<OsString>) -> i32 {
+    let cli = Cli::parse_from(args);
+    env_logger::Builder::from_default_env()
+        .filter_level(if cli.verbose { log::LevelFilter::Info } else { log::LevelFilter::Warn })
+        .init();
+
+    match execute(cli.command) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {:#}", e);
+            1
+        }
+    }
+}
+
+fn execute(command: Command) -> Result<i32> {
+    match command {
+        Command::New { name, parent, comment, inherits, layout } => {
+            let theme_dir = parent.join(&name);
+            if theme_dir.join(PROJECT_FILE_NAME).exists() {
+                anyhow::bail!("{} already contains a project", theme_dir.display());
+            }
+            let mut project = IconProject::new(name.clone());
+            project.theme_name = name;
+            project.output_path = Some(theme_dir.clone());
+            if let Some(comment) = comment {
+                project.theme_comment = comment;
+            }
+            if let Some(inherits) = inherits {
+                project.fallback_themes = inherits;
+            }
+            if let Some(layout) = layout {
+                project.layout = ThemeLayout::from_preset(&layout)
+                    .with_context(|| format!("Unknown layout preset: {}", layout))?;
+            }
+            std::fs::create_dir_all(&theme_dir)
+                .with_context(|| format!("Failed to create {}", theme_dir.display()))?;
+            save_project(&theme_dir, &project)?;
+            println!("Created project in {}", theme_dir.display());
+        }
+        Command::Add { project: dir, icon, file, size, scale, link } => {
+            let mut project = load_project(&dir)?;
+            let mut file = file.canonicalize()
+                .with_context(|| format!("Replacement file not found: {}", file.display()))?;
+            if project.vendor_assets {
+                file = assets::import_asset(&dir, &file)?;
+            }
+            match size {
+                None => {
+                    project.set_replacement(&icon, file);
+                    project.set_replacement_link(&icon, link);
+                }
+                Some(size) if scale == 1 => {
+                    project.set_size_replacement(&icon, size, file);
+                    project.set_size_replacement_link(&icon, size, link);
+                }
+                Some(size) => {
+                    project.set_scaled_replacement(&icon, size, scale, file);
+                    project.set_scaled_replacement_link(&icon, size, scale, link);
+                }
+            }
+            save_project(&dir, &project)?;
+        }
+        Command::Remove { project: dir, icon, size, scale } => {
+            let mut project = load_project(&dir)?;
+            let found = match size {
+                None => project.remove_replacement(&icon),
+                Some(size) if scale == 1 => project.remove_size_replacement(&icon, size),
+                Some(size) => project.remove_scaled_replacement(&icon, size, scale),
+            };
+            if !found {
+                anyhow::bail!("{} has no such replacement", icon);
+            }
+            save_project(&dir, &project)?;
+        }
+        Command::Link { project: dir, icon, size, scale, off } => {
+            let mut project = load_project(&dir)?;
+            let found = match size {
+                None => project.set_replacement_link(&icon, !off),
+                Some(size) if scale == 1 => project.set_size_replacement_link(&icon, size, !off),
+                Some(size) => project.set_scaled_replacement_link(&icon, size, scale, !off),
+            };
+            if !found {
+                anyhow::bail!("{} has no such replacement", icon);
+            }
+            save_project(&dir, &project)?;
+        }
+        Command::Alias { project: dir, icon, alias, remove } => {
+            let mut project = load_project(&dir)?;
+            if remove {
+                if project.icon_aliases.get(&alias) != Some(&icon) {
+                    anyhow::bail!("{} is not an alias of {}", alias, icon);
+                }
+                project.remove_alias(&alias);
+            } else if !project.set_alias(&alias, &icon) {
+                anyhow::bail!("Cannot alias {} to {}, aliases can't refer to each other", alias, icon);
+            }
+            save_project(&dir, &project)?;
+        }
+        Command::Vendor { project: dir, off } => {
+            let mut project = load_project(&dir)?;
+            project.vendor_assets = !off;
+            if !off {
+                let imported = assets::import_assets(&mut project, &dir, &mut BTreeMap::new())?;
+                println!("Imported {} files into {}", imported, dir.join(assets::ASSETS_DIR_NAME).display());
+            }
+            save_project(&dir, &project)?;
+        }
+        Command::SetCategory { project: dir, icon, category } => {
+            let mut project = load_project(&dir)?;
+            if project.layout.contexts.iter().all(|c| c.category != category) {
+                log::warn!("Layout has no context for category {}, the first one is used", category);
+            }
+            project.icon_categories.insert(icon, category);
+            save_project(&dir, &project)?;
+        }
+        Command::Generate { project: dir, output, dry_run } => {
+            let project = load_project(&dir)?;
+            let output_dir = output.unwrap_or_else(|| dir.clone());
+            let generator = ThemePackGenerator::from_project(&project, project.display_theme_name(), output_dir.clone());
+            if dry_run {
+                let plan = generator.plan()?;
+                for warning in &plan.warnings {
+                    println!("warning: {}", warning);
+                }
+                for file in &plan.files {
+                    println!("{}", file.describe());
+                }
+                for path in &plan.deletions {
+                    println!("Delete {}", path.display());
+                }
+                return Ok(0);
+            }
+            let report = generator.generate()?;
+            for path in &report.modified {
+                println!("Kept modified file: {}", path.display());
+            }
+            println!(
+                "Generated {} ({} unchanged, {} removed)",
+                output_dir.display(),
+                report.unchanged.len(),
+                report.removed.len()
+            );
+        }
+        Command::List { project: dir } => {
+            let project = load_project(&dir)?;
+            for icon in project.replaced_icon_names() {
+                let category = project.icon_categories.get(icon).map(String::as_str).unwrap_or("Applications");
+                println!("{} [{}]", icon, category);
+                let aliases = project.aliases_of(icon);
+                if !aliases.is_empty() {
+                    let aliases: Vec<&str> = aliases.into_iter().map(String::as_str).collect();
+                    println!("  aliases: {}", aliases.join(", "));
+                }
+                if let Some(path) = project.icon_replacements.get(icon) {
+                    let linked = project.icon_links.get(icon).copied().unwrap_or(false);
+                    println!("  all sizes: {}{}", path.display(), if linked { " (link)" } else { "" });
+                }
+                for (size, path) in project.size_specific_replacements.get(icon).into_iter().flatten() {
+                    let linked = project.size_specific_links.get(icon).and_then(|m| m.get(size)).copied().unwrap_or(false);
+                    println!("  {}: {}{}", size, path.display(), if linked { " (link)" } else { "" });
+                }
+                for (scale, size_map) in project.scaled_replacements.get(icon).into_iter().flatten() {
+                    for (size, path) in size_map {
+                        let linked = project.scaled_links.get(icon)
+                            .and_then(|m| m.get(scale))
+                            .and_then(|m| m.get(size))
+                            .copied()
+                            .unwrap_or(false);
+                        println!("  {}@{}x: {}{}", size, scale, path.display(), if linked { " (link)" } else { "" });
+                    }
+                }
+            }
+        }
+        Command::Validate { project: dir } => {
+            let project = load_project(&dir)?;
+            let generator = ThemePackGenerator::from_project(&project, project.display_theme_name(), dir);
+            let plan = generator.plan()?;
+            for warning in &plan.warnings {
+                println!("{}", warning);
+            }
+            if !plan.warnings.is_empty() {
+                return Ok(1);
+            }
+            println!("No problems found");
+        }
+    }
+    Ok(0)
+}
+
+fn load_project(dir: &Path) -> Result<IconProject> {
+    let path = dir.join(PROJECT_FILE_NAME);
+    let mut project = IconProject::load(&path)
+        .with_context(|| format!("Failed to load project {}", path.display()))?;
+    project.output_path = Some(dir.to_path_buf());
+    Ok(project)
+}
+
+// Also drops assets the saved project doesn't refer to any more
+fn save_project(dir: &Path, project: &IconProject) -> Result<()> {
+    let path = dir.join(PROJECT_FILE_NAME);
+    project.save(&path)
+        .with_context(|| format!("Failed to save project {}", path.display()))?;
+    assets::collect_garbage(project, dir)?;
+    Ok(())
+}
@@ -1,10 +1,13 @@
-use qmetaobject::{prelude::*, QAbstractListModel, QModelIndex, QVariant, QByteArray, QVariantMap};
-use std::collections::HashMap;
+use qmetaobject::{prelude::*, queued_callback, QAbstractListModel, QPointer, QModelIndex, QVariant, QByteArray, QVariantMap};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::thread;
-use serde::{Deserialize, Serialize};
 
-use crate::icon_catalog::IconCatalog;
+use icon_packer_core::catalog_cache::CatalogCache;
+use icon_packer_core::catalog_watcher::CatalogWatcher;
+use icon_packer_core::icon_catalog::{is_symbolic, symbolic_name, IconCatalog, IconFormat};
+use icon_packer_core::icon_lookup::fallback_names;
 
 #[derive(QObject, Default)]
 pub struct IconModel {
@@ -18,11 +21,34 @@ pub struct IconModel {
     category_filter: qt_property!(QString; NOTIFY category_filter_changed),
     category_filter_changed: qt_signal!(),
     _catalog_data: Arc<Mutex<Option<IconCatalog>>>,
+    // Themes whose icons the grid shows, together with what they inherit. Empty shows
+    // every installed theme.
+    _theme_names: Arc<Mutex<Vec<String>>>,
     _all_icons_data: Arc<Mutex<Vec<IconItem>>>, // Full unfiltered list
     _icons_data: Arc<Mutex<Vec<IconItem>>>,
     _loading_flag: Arc<Mutex<bool>>,
+    // Handed from the loading thread to the watcher once the first load is done
+    _catalog_cache: Arc<Mutex<Option<CatalogCache>>>,
+    _catalog_watcher: Option<CatalogWatcher>,
     load_catalog: qt_method!(fn load_catalog(&mut self) {
-        self.load_catalog_async();
+        self.load_catalog_async(true);
+    }),
+    refresh_catalog: qt_method!(fn refresh_catalog(&mut self) {
+        self.load_catalog_async(false);
+    }),
+    set_themes: qt_method!(fn set_themes(&mut self, themes: String) {
+        let themes: Vec<String> = themes
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+        if *self._theme_names.lock().unwrap() == themes {
+            return;
+        }
+        *self._theme_names.lock().unwrap() = themes;
+        rebuild_icons(&self._catalog_data, &self._all_icons_data, &self._theme_names);
+        self.apply_filters();
+        self.catalog_changed();
     }),
     set_search_text: qt_method!(fn set_search_text(&mut self, text: String) {
         self.search_text = text.into();
@@ -75,15 +101,46 @@ pub struct IconModel {
             .unwrap_or_else(|| "Applications".to_string())
             .into()
     }),
+    // Name of the icon's symbolic version, empty if the themes have none
+    get_symbolic_name: qt_method!(fn get_symbolic_name(&self, icon_name: String) -> QString {
+        let icons = self._all_icons_data.lock().unwrap();
+        icons.iter()
+            .find(|icon| icon.name == icon_name)
+            .map(|icon| icon.symbolic.as_str())
+            .unwrap_or_default()
+            .into()
+    }),
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug)]
 struct IconItem {
     name: String,
     category: String,
     has_svg: bool,
     has_png: bool,
     replacement_path: Option<String>,
+    theme: String,
+    symbolic: String,
+    // The more generic name the project replaces that lookups of this icon fall back to
+    generic_replacement: Option<String>,
+}
+
+impl IconItem {
+    fn is_replaced(&self) -> bool {
+        self.replacement_path.as_ref().is_some_and(|path| !path.is_empty())
+    }
+
+    // How the generated theme provides the icon: "replaced" by the project itself,
+    // "generic" through a replaced fallback name, or only "inherited" from the themes
+    fn coverage(&self) -> &'static str {
+        if self.is_replaced() {
+            "replaced"
+        } else if self.generic_replacement.is_some() {
+            "generic"
+        } else {
+            "inherited"
+        }
+    }
 }
 
 impl QAbstractListModel for IconModel {
@@ -104,6 +161,10 @@ impl QAbstractListModel for IconModel {
             2 => icon.has_svg.into(),
             3 => icon.has_png.into(),
             4 => QString::from(icon.replacement_path.as_ref().map(|s| s.as_str()).unwrap_or("")).into(),
+            5 => QString::from(icon.theme.as_str()).into(),
+            6 => QString::from(icon.symbolic.as_str()).into(),
+            7 => QString::from(icon.coverage()).into(),
+            8 => QString::from(icon.generic_replacement.as_deref().unwrap_or_default()).into(),
             _ => QVariant::default(),
         }
     }
@@ -115,68 +176,49 @@ impl QAbstractListModel for IconModel {
         hash.insert(2, "hasSvg".into());
         hash.insert(3, "hasPng".into());
         hash.insert(4, "replacementPath".into());
+        hash.insert(5, "theme".into());
+        hash.insert(6, "symbolicName".into());
+        hash.insert(7, "coverage".into());
+        hash.insert(8, "genericName".into());
         hash
     }
 }
 
 impl IconModel {
-    fn load_catalog_async(&mut self) {
+    // With `use_cache` themes that look unchanged since the last scan are taken from the
+    // cache; without it every theme is scanned again and watching restarts afterwards,
+    // picking up changed search paths.
+    fn load_catalog_async(&mut self, use_cache: bool) {
+        if !use_cache {
+            self._catalog_watcher = None;
+        }
         self.loading = true.into();
         self.loading_changed();
         *self._loading_flag.lock().unwrap() = true;
         
         let all_icons_data = Arc::clone(&self._all_icons_data);
         let catalog_data = Arc::clone(&self._catalog_data);
+        let catalog_cache = Arc::clone(&self._catalog_cache);
+        let theme_names = Arc::clone(&self._theme_names);
         let loading_flag = Arc::clone(&self._loading_flag);
-
-        let cache_path = std::env::var("HOME")
-            .map(|home| format!("{}/.local/share/icon-packer/catalog.json", home))
-            .unwrap_or_else(|_| ".local/share/icon-packer/catalog.json".to_string());
         
-        let cache_path_clone = cache_path.clone();
         thread::spawn(move || {
-            if let Ok(cached) = std::fs::read_to_string(&cache_path_clone) {
-                if let Ok(icons) = serde_json::from_str::<Vec<IconItem>>(&cached) {
-                    log::info!("Loaded {} icons from cache", icons.len());
-                    *all_icons_data.lock().unwrap() = icons.clone();
-                    *loading_flag.lock().unwrap() = false;
-                    log::info!("Cache load complete, loading flag set to false");
-                    return;
-                } else {
-                    log::warn!("Failed to parse cached icons, will reload from disk");
-                }
-            }
+            let cache_path = catalog_cache_path();
+            let cache = if use_cache {
+                CatalogCache::load(&cache_path)
+            } else {
+                CatalogCache::default()
+            };
             
-            match IconCatalog::discover(None) {
-                Ok(catalog) => {
-                    *catalog_data.lock().unwrap() = Some(catalog.clone());
-                    let mut icons = Vec::new();
-                    for icon_meta in catalog.iter() {
-                        let has_svg = icon_meta.variants.iter().any(|v| v.format == crate::icon_catalog::IconFormat::Svg);
-                        let has_png = icon_meta.variants.iter().any(|v| v.format == crate::icon_catalog::IconFormat::Png);
-                        let category = icon_meta.variants.first()
-                            .map(|v| v.directory.context.clone())
-                            .unwrap_or_else(|| "Generic".to_string());
-                        icons.push(IconItem {
-                            name: icon_meta.name.clone(),
-                            category,
-                            has_svg,
-                            has_png,
-                            replacement_path: None,
-                        });
-                    }
-                    icons.sort_by(|a, b| a.name.cmp(&b.name));
-                    
-                    if let Ok(json) = serde_json::to_string_pretty(&icons) {
-                        if let Some(parent) = std::path::Path::new(&cache_path_clone).parent() {
-                            let _ = std::fs::create_dir_all(parent);
-                            let _ = std::fs::write(&cache_path_clone, json);
-                        }
+            match IconCatalog::discover_with_cache(None, &cache) {
+                Ok((catalog, new_cache)) => {
+                    if let Err(e) = new_cache.save(&cache_path) {
+                        log::warn!("Failed to write catalog cache: {:?}", e);
                     }
-                    
-                    *all_icons_data.lock().unwrap() = icons.clone();
+                    *catalog_cache.lock().unwrap() = Some(new_cache);
+                    let count = store_catalog(catalog, &catalog_data, &all_icons_data, &theme_names);
                     *loading_flag.lock().unwrap() = false;
-                    log::info!("Icon discovery complete, {} icons loaded, loading flag set to false", icons.len());
+                    log::info!("Icon discovery complete, {} icons loaded, loading flag set to false", count);
                 }
                 Err(err) => {
                     log::error!("Failed to load icon catalog: {:?}", err);
@@ -185,6 +227,39 @@ impl IconModel {
             }
         });
     }
+
+    // Keeps the catalog in sync with icons installed or removed while the app runs
+    fn start_watching(&mut self) {
+        if self._catalog_watcher.is_some() {
+            return;
+        }
+        let Some(cache) = self._catalog_cache.lock().unwrap().take() else {
+            return;
+        };
+        let catalog_data = Arc::clone(&self._catalog_data);
+        let all_icons_data = Arc::clone(&self._all_icons_data);
+        let theme_names = Arc::clone(&self._theme_names);
+        let ptr = QPointer::from(&*self);
+        let refresh = queued_callback(move |()| {
+            if let Some(model) = ptr.as_pinned() {
+                let mut model = model.borrow_mut();
+                model.apply_filters();
+                model.catalog_changed();
+            }
+        });
+        let watcher = CatalogWatcher::new(cache, move |catalog, cache| {
+            if let Err(e) = cache.save(&catalog_cache_path()) {
+                log::warn!("Failed to write catalog cache: {:?}", e);
+            }
+            let count = store_catalog(catalog, &catalog_data, &all_icons_data, &theme_names);
+            log::info!("Icon catalog updated from disk, {} icons", count);
+            refresh(());
+        });
+        match watcher {
+            Ok(watcher) => self._catalog_watcher = Some(watcher),
+            Err(e) => log::warn!("Not watching icon directories: {:?}", e),
+        }
+    }
     
     fn apply_filters(&mut self) {
         let all_icons = self._all_icons_data.lock().unwrap().clone();
@@ -194,7 +269,10 @@ impl IconModel {
         let filtered: Vec<IconItem> = all_icons.into_iter()
             .filter(|icon| {
                 let matches_search = search.is_empty() || icon.name.to_lowercase().contains(&search);
-                let matches_category = category.is_empty() || category == "All Categories" || icon.category == category;
+                let matches_category = category.is_empty()
+                    || category == "All Categories"
+                    || icon.category == category
+                    || (category == "Symbolic" && is_symbolic(&icon.name));
                 matches_search && matches_category
             })
             .collect();
@@ -233,46 +311,119 @@ impl IconModel {
                 self.loading_changed();
                 self.catalog_changed();
                 log::info!("Icon loading complete, {} icons available", all_icons_len);
+                self.start_watching();
             }
         }
     }
 
+    // Replacing an icon can change the coverage of every more specific name, so the
+    // whole list is refreshed rather than the one row
     fn set_replacement_internal(&mut self, icon_name: String, file_path: String) {
         let mut all_icons = self._all_icons_data.lock().unwrap();
         if let Some(icon) = all_icons.iter_mut().find(|i| i.name == icon_name) {
-            icon.replacement_path = Some(file_path.clone());
-        }
-        drop(all_icons);
-        
-        let mut icons = self._icons_data.lock().unwrap();
-        if let Some(icon) = icons.iter_mut().find(|i| i.name == icon_name) {
             icon.replacement_path = Some(file_path);
-            let row = icons.iter()
-                .position(|i| i.name == icon_name)
-                .map(|i| i as i32)
-                .unwrap_or(-1);
-            drop(icons);
-            if row >= 0 {
-                let index = self.row_index(row);
-                self.data_changed(index, index);
-            }
         }
+        update_generic_replacements(&mut all_icons);
+        drop(all_icons);
+        self.apply_filters();
     }
 
     fn clear_replacement_internal(&mut self, icon_name: String) {
-        let mut icons = self._icons_data.lock().unwrap();
-        if let Some(icon) = icons.iter_mut().find(|i| i.name == icon_name) {
+        let mut all_icons = self._all_icons_data.lock().unwrap();
+        if let Some(icon) = all_icons.iter_mut().find(|i| i.name == icon_name) {
             icon.replacement_path = None;
-            let row = icons.iter()
-                .position(|i| i.name == icon_name)
-                .map(|i| i as i32)
-                .unwrap_or(-1);
-            drop(icons);
-            if row >= 0 {
-                let index = self.row_index(row);
-                self.data_changed(index, index);
-            }
         }
+        update_generic_replacements(&mut all_icons);
+        drop(all_icons);
+        self.apply_filters();
     }
 }
 
+fn catalog_cache_path() -> PathBuf {
+    std::env::var("HOME")
+        .map(|home| PathBuf::from(home).join(".local/share/icon-packer/catalog.json"))
+        .unwrap_or_else(|_| PathBuf::from(".local/share/icon-packer/catalog.json"))
+}
+
+fn store_catalog(
+    catalog: IconCatalog,
+    catalog_data: &Mutex<Option<IconCatalog>>,
+    all_icons_data: &Mutex<Vec<IconItem>>,
+    theme_names: &Mutex<Vec<String>>,
+) -> usize {
+    *catalog_data.lock().unwrap() = Some(catalog);
+    rebuild_icons(catalog_data, all_icons_data, theme_names)
+}
+
+// Rebuilds the icon list from the catalog for the selected themes. Replacements the
+// project already set on the old list are kept, also for icons the themes don't have.
+// Returns the number of icons.
+fn rebuild_icons(
+    catalog_data: &Mutex<Option<IconCatalog>>,
+    all_icons_data: &Mutex<Vec<IconItem>>,
+    theme_names: &Mutex<Vec<String>>,
+) -> usize {
+    let catalog = catalog_data.lock().unwrap();
+    let Some(catalog) = catalog.as_ref() else {
+        return 0;
+    };
+    let theme_names = theme_names.lock().unwrap().clone();
+    let metadata: Vec<_> = if theme_names.is_empty() {
+        catalog.iter().collect()
+    } else {
+        catalog.icons_for_themes(&theme_names).collect()
+    };
+
+    let mut all_icons = all_icons_data.lock().unwrap();
+    let mut replaced: HashMap<String, IconItem> = all_icons
+        .drain(..)
+        .filter(|icon| icon.replacement_path.is_some())
+        .map(|icon| (icon.name.clone(), icon))
+        .collect();
+    for icon_meta in metadata {
+        let has_svg = icon_meta.variants.iter().any(|v| v.format == IconFormat::Svg);
+        let has_png = icon_meta.variants.iter().any(|v| v.format == IconFormat::Png);
+        // The first variant is from the theme the icon is looked up in
+        let first = icon_meta.variants.first();
+        let category = first
+            .map(|v| v.directory.context.clone())
+            .unwrap_or_else(|| "Generic".to_string());
+        let theme = first.map(|v| v.theme_name.clone()).unwrap_or_default();
+        let replacement_path = replaced.remove(&icon_meta.name).and_then(|icon| icon.replacement_path);
+        let symbolic = if icon_meta.symbolic.is_empty() {
+            String::new()
+        } else {
+            symbolic_name(&icon_meta.name)
+        };
+        all_icons.push(IconItem {
+            name: icon_meta.name,
+            category,
+            has_svg,
+            has_png,
+            replacement_path,
+            theme,
+            symbolic,
+            generic_replacement: None,
+        });
+    }
+    all_icons.extend(replaced.into_values());
+    all_icons.sort_by(|a, b| a.name.cmp(&b.name));
+    update_generic_replacements(&mut all_icons);
+    all_icons.len()
+}
+
+// Finds for every icon the most specific fallback name the project replaces, the icon
+// a desktop would show from the generated theme instead of the inherited one
+fn update_generic_replacements(icons: &mut [IconItem]) {
+    let replaced: HashSet<String> = icons
+        .iter()
+        .filter(|icon| icon.is_replaced())
+        .map(|icon| icon.name.clone())
+        .collect();
+    for icon in icons {
+        icon.generic_replacement = fallback_names(&icon.name)
+            .into_iter()
+            .skip(1)
+            .find(|name| replaced.contains(name));
+    }
+}
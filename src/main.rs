@@ -1,9 +1,6 @@
-mod icon_catalog;
+mod cli;
 mod icon_model;
-mod icon_theme;
-mod project;
 mod project_manager;
-mod theme_generator;
 mod theme_manager;
 
 use cstr::cstr;
@@ -21,10 +18,17 @@ qrc!(root_qml,
         "qml/new_project_dialog.qml" as "new_project_dialog.qml",
         "qml/about_sheet.qml" as "about_sheet.qml",
         "qml/project_settings_dialog.qml" as "project_settings_dialog.qml",
+        "qml/generate_dialog.qml" as "generate_dialog.qml",
+        "qml/search_paths_dialog.qml" as "search_paths_dialog.qml",
     }
 );
 
 fn main() {
+    let args: Vec<_> = std::env::args_os().collect();
+    if cli::is_cli_invocation(&args) {
+        std::process::exit(cli::run(args));
+    }
+
     std::env::set_var(
         "QT_LOGGING_RULES",
         "*.debug=false;qml.debug=false;*.warning=true;*.critical=true",
@@ -1,10 +1,13 @@
-use qmetaobject::{prelude::*, QVariantMap, QString};
-use std::collections::BTreeMap;
+use qmetaobject::{prelude::*, queued_callback, QPointer, QVariantMap, QString};
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
-use crate::project::IconProject;
-use crate::theme_generator::ThemePackGenerator;
+use icon_packer_core::assets;
+use icon_packer_core::live_generation::{LiveEvent, LiveGenerationWorker, LiveJob};
+use icon_packer_core::project::{IconProject, PROJECT_FILE_NAME};
+use icon_packer_core::staging::StagedOutput;
+use icon_packer_core::theme_generator::ThemePackGenerator;
+use icon_packer_core::theme_layout::ThemeLayout;
 
 #[derive(QObject, Default)]
 pub struct ProjectManager {
@@ -15,7 +18,14 @@ pub struct ProjectManager {
     project_name_changed: qt_signal!(),
     has_project: qt_property!(bool; NOTIFY has_project_changed),
     has_project_changed: qt_signal!(),
+    generating: qt_property!(bool; NOTIFY generating_changed),
+    generating_changed: qt_signal!(),
+    generation_progress: qt_signal!(done: u32, total: u32),
+    generation_finished: qt_signal!(),
+    generation_failed: qt_signal!(message: QString),
+    export_finished: qt_signal!(output_path: QString),
     _project: Arc<Mutex<Option<IconProject>>>,
+    _live_worker: Option<LiveGenerationWorker>,
     
     new_project: qt_method!(fn new_project(&mut self, name: String, output_path: String) {
         let mut project = IconProject::new(name.clone());
@@ -42,7 +52,7 @@ pub struct ProjectManager {
             return false;
         }
         
-        let metadata_path = theme_path_buf.join(".icon-packer-project.json");
+        let metadata_path = theme_path_buf.join(PROJECT_FILE_NAME);
         
         if !metadata_path.exists() {
             log::warn!("Metadata file not found at {:?}, creating new project from theme folder", metadata_path);
@@ -88,11 +98,9 @@ pub struct ProjectManager {
             let mut project = self._project.lock().unwrap();
             if let Some(ref mut proj) = *project {
                 if file_path.is_empty() {
-                    proj.icon_replacements.remove(&icon_name);
-                    proj.icon_links.remove(&icon_name);
-                    proj.icon_categories.remove(&icon_name);
+                    proj.remove_replacement(&icon_name);
                 } else {
-                    proj.icon_replacements.insert(icon_name.clone(), PathBuf::from(file_path));
+                    proj.set_replacement(&icon_name, PathBuf::from(file_path));
                 }
                 true
             } else {
@@ -116,12 +124,7 @@ pub struct ProjectManager {
         let needs_generate = {
             let mut project = self._project.lock().unwrap();
             if let Some(ref mut proj) = *project {
-                if proj.icon_replacements.contains_key(&icon_name) {
-                    proj.icon_links.insert(icon_name, is_link);
-                    true
-                } else {
-                    false
-                }
+                proj.set_replacement_link(&icon_name, is_link)
             } else {
                 false
             }
@@ -136,19 +139,7 @@ pub struct ProjectManager {
         let needs_generate = {
             let mut project = self._project.lock().unwrap();
             if let Some(ref mut proj) = *project {
-                if let Some(size_map) = proj.size_specific_replacements.get(&icon_name) {
-                    if size_map.contains_key(&size) {
-                        proj.size_specific_links
-                            .entry(icon_name)
-                            .or_insert_with(BTreeMap::new)
-                            .insert(size, is_link);
-                        true
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                }
+                proj.set_size_replacement_link(&icon_name, size, is_link)
             } else {
                 false
             }
@@ -203,7 +194,7 @@ pub struct ProjectManager {
         let mut map = QVariantMap::default();
         if let Some(ref proj) = *project {
             for (name, path) in &proj.icon_replacements {
-                map.insert(QString::from(name.as_str()).into(), QString::from(path.to_string_lossy().as_ref()).into());
+                map.insert(QString::from(name.as_str()).into(), QString::from(proj.resolve_path(path).to_string_lossy().as_ref()).into());
             }
         }
         map
@@ -313,16 +304,10 @@ pub struct ProjectManager {
         let needs_generate = {
             let mut project = self._project.lock().unwrap();
             if let Some(ref mut proj) = *project {
-                let size_map = proj.size_specific_replacements
-                    .entry(icon_name.clone())
-                    .or_insert_with(BTreeMap::new);
                 if file_path.is_empty() {
-                    size_map.remove(&size);
-                    if size_map.is_empty() {
-                        proj.size_specific_replacements.remove(&icon_name);
-                    }
+                    proj.remove_size_replacement(&icon_name, size);
                 } else {
-                    size_map.insert(size, PathBuf::from(file_path));
+                    proj.set_size_replacement(&icon_name, size, PathBuf::from(file_path));
                 }
                 true
             } else {
@@ -343,7 +328,7 @@ pub struct ProjectManager {
                 for (size, path) in size_map {
                     map.insert(
                         QString::from(size.to_string().as_str()).into(),
-                        QString::from(path.to_string_lossy().as_ref()).into()
+                        QString::from(proj.resolve_path(path).to_string_lossy().as_ref()).into()
                     );
                 }
             }
@@ -351,121 +336,358 @@ pub struct ProjectManager {
         map
     }),
     
-    generate_theme: qt_method!(fn generate_theme(&self, theme_name: String, output_path: String) -> bool {
+    add_scaled_replacement: qt_method!(fn add_scaled_replacement(&mut self, icon_name: String, size: u32, scale: u32, file_path: String) {
+        let needs_generate = {
+            let mut project = self._project.lock().unwrap();
+            if let Some(ref mut proj) = *project {
+                if file_path.is_empty() {
+                    proj.remove_scaled_replacement(&icon_name, size, scale);
+                } else {
+                    proj.set_scaled_replacement(&icon_name, size, scale, PathBuf::from(file_path));
+                }
+                true
+            } else {
+                false
+            }
+        };
+        self.current_project_changed();
+        if needs_generate {
+            self.generate_theme_live();
+        }
+    }),
+    
+    set_scaled_replacement_link: qt_method!(fn set_scaled_replacement_link(&mut self, icon_name: String, size: u32, scale: u32, is_link: bool) {
+        let needs_generate = {
+            let mut project = self._project.lock().unwrap();
+            if let Some(ref mut proj) = *project {
+                proj.set_scaled_replacement_link(&icon_name, size, scale, is_link)
+            } else {
+                false
+            }
+        };
+        self.current_project_changed();
+        if needs_generate {
+            self.generate_theme_live();
+        }
+    }),
+    
+    get_scaled_replacements: qt_method!(fn get_scaled_replacements(&self, icon_name: String, scale: u32) -> QVariantMap {
         let project = self._project.lock().unwrap();
+        let mut map = QVariantMap::default();
         if let Some(ref proj) = *project {
-            let output_dir = PathBuf::from(output_path.clone());
-            let mut generator = ThemePackGenerator::new(theme_name.clone(), output_dir);
-            
-            let theme_comment = if !proj.theme_comment.is_empty() {
-                proj.theme_comment.clone()
-            } else {
-                "Icon theme generated by icon-packer".to_string()
-            };
-            generator.set_theme_comment(theme_comment);
-            
-            generator.set_fallback_themes(proj.fallback_themes.clone());
-            
-            for (icon_name, path) in &proj.icon_replacements {
-                generator.add_replacement(icon_name.clone(), path.clone());
-            }
-            
-            for (icon_name, size_map) in &proj.size_specific_replacements {
+            if let Some(size_map) = proj.scaled_replacements.get(&icon_name).and_then(|m| m.get(&scale)) {
                 for (size, path) in size_map {
-                    generator.add_size_replacement(icon_name.clone(), *size, path.clone());
+                    map.insert(
+                        QString::from(size.to_string().as_str()).into(),
+                        QString::from(proj.resolve_path(path).to_string_lossy().as_ref()).into()
+                    );
                 }
             }
-            
-            match generator.generate() {
-                Ok(_) => {
-                    log::info!("Theme generated successfully to: {}", output_path);
+        }
+        map
+    }),
+    
+    // Comma-separated aliases of an icon
+    get_aliases: qt_method!(fn get_aliases(&self, icon_name: String) -> QString {
+        let project = self._project.lock().unwrap();
+        let Some(ref proj) = *project else {
+            return QString::default();
+        };
+        let aliases: Vec<&str> = proj.aliases_of(&icon_name).into_iter().map(String::as_str).collect();
+        aliases.join(",").into()
+    }),
+    
+    add_alias: qt_method!(fn add_alias(&mut self, icon_name: String, alias: String) -> bool {
+        let alias = alias.trim().to_string();
+        let added = {
+            let mut project = self._project.lock().unwrap();
+            match *project {
+                Some(ref mut proj) if !alias.is_empty() => proj.set_alias(&alias, &icon_name),
+                _ => false,
+            }
+        };
+        if added {
+            self.current_project_changed();
+            self.generate_theme_live();
+        }
+        added
+    }),
+    
+    remove_alias: qt_method!(fn remove_alias(&mut self, alias: String) {
+        let removed = {
+            let mut project = self._project.lock().unwrap();
+            match *project {
+                Some(ref mut proj) => proj.remove_alias(&alias),
+                None => false,
+            }
+        };
+        if removed {
+            self.current_project_changed();
+            self.generate_theme_live();
+        }
+    }),
+    
+    get_write_icon_cache: qt_method!(fn get_write_icon_cache(&self) -> bool {
+        let project = self._project.lock().unwrap();
+        project.as_ref().map(|proj| proj.write_icon_cache).unwrap_or(true)
+    }),
+    
+    set_write_icon_cache: qt_method!(fn set_write_icon_cache(&mut self, write: bool) {
+        let needs_generate = {
+            let mut project = self._project.lock().unwrap();
+            match *project {
+                Some(ref mut proj) if proj.write_icon_cache != write => {
+                    proj.write_icon_cache = write;
                     true
                 }
-                Err(e) => {
-                    log::error!("Failed to generate theme: {:?}", e);
-                    false
+                _ => false,
+            }
+        };
+        if needs_generate {
+            self.current_project_changed();
+            self.generate_theme_live();
+        }
+    }),
+    
+    get_allow_external_links: qt_method!(fn get_allow_external_links(&self) -> bool {
+        let project = self._project.lock().unwrap();
+        project.as_ref().map(|proj| proj.allow_external_links).unwrap_or(false)
+    }),
+    
+    set_allow_external_links: qt_method!(fn set_allow_external_links(&mut self, allow: bool) {
+        let needs_generate = {
+            let mut project = self._project.lock().unwrap();
+            match *project {
+                Some(ref mut proj) if proj.allow_external_links != allow => {
+                    proj.allow_external_links = allow;
+                    true
                 }
+                _ => false,
             }
-        } else {
-            false
+        };
+        if needs_generate {
+            self.current_project_changed();
+            self.generate_theme_live();
         }
     }),
     
+    get_vendor_assets: qt_method!(fn get_vendor_assets(&self) -> bool {
+        let project = self._project.lock().unwrap();
+        project.as_ref().map(|proj| proj.vendor_assets).unwrap_or(false)
+    }),
     
-    generate_theme_live: qt_method!(fn generate_theme_live(&mut self) {
-        let (output_path, theme_name, fallback_themes, icon_replacements, size_specific_replacements, icon_links, size_specific_links, icon_categories, project_clone) = {
-            let project = self._project.lock().unwrap();
-            if let Some(ref proj) = *project {
-                if let Some(ref output_path) = proj.output_path {
-                    let theme_name = if !proj.theme_name.is_empty() {
-                        proj.theme_name.clone()
-                    } else {
-                        proj.name.clone()
-                    };
-                    (
-                        Some(output_path.clone()),
-                        theme_name,
-                        proj.fallback_themes.clone(),
-                        proj.icon_replacements.clone(),
-                        proj.size_specific_replacements.clone(),
-                        proj.icon_links.clone(),
-                        proj.size_specific_links.clone(),
-                        proj.icon_categories.clone(),
-                        Some(proj.clone()),
-                    )
+    // Files are imported by the next live generation run
+    set_vendor_assets: qt_method!(fn set_vendor_assets(&mut self, vendor: bool) {
+        let needs_generate = {
+            let mut project = self._project.lock().unwrap();
+            match *project {
+                Some(ref mut proj) if proj.vendor_assets != vendor => {
+                    proj.vendor_assets = vendor;
+                    true
+                }
+                _ => false,
+            }
+        };
+        if needs_generate {
+            self.current_project_changed();
+            self.generate_theme_live();
+        }
+    }),
+    
+    get_layout_preset: qt_method!(fn get_layout_preset(&self) -> QString {
+        let project = self._project.lock().unwrap();
+        if let Some(ref proj) = *project {
+            proj.layout.preset_name().into()
+        } else {
+            QString::default()
+        }
+    }),
+    
+    set_layout_preset: qt_method!(fn set_layout_preset(&mut self, preset: String) {
+        let Some(layout) = ThemeLayout::from_preset(&preset) else {
+            log::warn!("Unknown layout preset: {}", preset);
+            return;
+        };
+        let needs_generate = {
+            let mut project = self._project.lock().unwrap();
+            if let Some(ref mut proj) = *project {
+                if proj.layout != layout {
+                    proj.layout = layout;
+                    true
                 } else {
-                    (None, String::new(), Vec::new(), BTreeMap::new(), BTreeMap::new(), BTreeMap::new(), BTreeMap::new(), BTreeMap::new(), None)
+                    false
                 }
             } else {
-                (None, String::new(), Vec::new(), BTreeMap::new(), BTreeMap::new(), BTreeMap::new(), BTreeMap::new(), BTreeMap::new(), None)
+                false
             }
         };
-        
-        if let Some(output_path) = output_path {
-            let mut generator = ThemePackGenerator::new(theme_name.clone(), output_path.clone());
-            generator.set_fallback_themes(fallback_themes.clone());
-            
-            for (icon_name, path) in icon_replacements {
-                generator.add_replacement(icon_name.clone(), path);
-                if let Some(&is_link) = icon_links.get(&icon_name) {
-                    generator.set_replacement_link(icon_name.clone(), is_link);
+        if needs_generate {
+            self.current_project_changed();
+            self.generate_theme_live();
+        }
+    }),
+    
+    get_layout_sizes: qt_method!(fn get_layout_sizes(&self) -> QString {
+        let project = self._project.lock().unwrap();
+        let layout = project.as_ref().map(|p| p.layout.clone()).unwrap_or_default();
+        let sizes: Vec<String> = layout.sized_entries().map(|s| s.size.to_string()).collect();
+        sizes.join(",").into()
+    }),
+    
+    has_previous_generation: qt_method!(fn has_previous_generation(&self) -> bool {
+        let project = self._project.lock().unwrap();
+        project.as_ref()
+            .and_then(|proj| proj.output_path.as_ref())
+            .map(|path| StagedOutput::new(path).has_previous())
+            .unwrap_or(false)
+    }),
+    
+    restore_previous_generation: qt_method!(fn restore_previous_generation(&mut self) -> bool {
+        let output_path = {
+            let project = self._project.lock().unwrap();
+            project.as_ref().and_then(|proj| proj.output_path.clone())
+        };
+        let Some(output_path) = output_path else {
+            return false;
+        };
+        // Waits for a live run on the same theme to finish, they share the staging folders
+        match StagedOutput::new(&output_path).restore_previous() {
+            Ok(()) => {
+                log::info!("Restored previous version of {}", output_path.display());
+                // The restored theme carries the project metadata it was generated from
+                self.load_project(output_path.to_string_lossy().to_string())
+            }
+            Err(e) => {
+                log::error!("Failed to restore previous theme version: {:?}", e);
+                false
+            }
+        }
+    }),
+    
+    // Describes what generate_theme would do without writing anything
+    plan_theme: qt_method!(fn plan_theme(&self, theme_name: String, output_path: String) -> QVariantMap {
+        let mut result = QVariantMap::default();
+        let project = self._project.lock().unwrap();
+        let Some(ref proj) = *project else {
+            return result;
+        };
+        let generator = ThemePackGenerator::from_project(proj, theme_name, PathBuf::from(output_path));
+        match generator.plan() {
+            Ok(plan) => {
+                let unchanged = plan.files.iter().filter(|file| file.up_to_date).count();
+                let files: Vec<String> = plan.files.iter().map(|file| file.describe()).collect();
+                let deletions: Vec<String> = plan.deletions.iter()
+                    .map(|path| format!("Delete {}", path.display()))
+                    .collect();
+                let directories: Vec<String> = plan.directories.iter()
+                    .map(|dir| dir.name.clone())
+                    .collect();
+                let summary = format!(
+                    "{} files to write, {} unchanged, {} to delete, {} directories, {} warnings",
+                    plan.files.len() - unchanged, unchanged, deletions.len(), directories.len(), plan.warnings.len()
+                );
+                result.insert("summary".into(), QString::from(summary).into());
+                result.insert("files".into(), QString::from(files.join("\n")).into());
+                result.insert("deletions".into(), QString::from(deletions.join("\n")).into());
+                result.insert("directories".into(), QString::from(directories.join("\n")).into());
+                result.insert("warnings".into(), QString::from(plan.warnings.join("\n")).into());
+            }
+            Err(e) => {
+                log::error!("Failed to plan theme generation: {:?}", e);
+                result.insert("error".into(), QString::from(format!("{:#}", e)).into());
+            }
+        }
+        result
+    }),
+
+    // Runs on the generation worker, export_finished or generation_failed tell how it went
+    generate_theme: qt_method!(fn generate_theme(&mut self, theme_name: String, output_path: String) -> bool {
+        let job = {
+            let project = self._project.lock().unwrap();
+            let Some(ref proj) = *project else {
+                return false;
+            };
+            LiveJob {
+                project: proj.clone(),
+                output_path: PathBuf::from(output_path),
+            }
+        };
+        self.live_worker().export(job, theme_name);
+        true
+    }),
+    
+    
+    // Schedules a background run; bursts of edits are merged and a newer edit cancels a running one
+    generate_theme_live: qt_method!(fn generate_theme_live(&mut self) {
+        let job = {
+            let project = self._project.lock().unwrap();
+            let Some(ref proj) = *project else {
+                return;
+            };
+            let Some(ref output_path) = proj.output_path else {
+                return;
+            };
+            LiveJob {
+                project: proj.clone(),
+                output_path: output_path.clone(),
+            }
+        };
+
+        self.live_worker().submit(job);
+    }),
+}
+
+impl ProjectManager {
+    fn live_worker(&mut self) -> &LiveGenerationWorker {
+        if self._live_worker.is_none() {
+            let ptr = QPointer::from(&*self);
+            let notify = queued_callback(move |event: LiveEvent| {
+                if let Some(manager) = ptr.as_pinned() {
+                    manager.borrow_mut().handle_live_event(event);
                 }
-                let category = icon_categories.get(&icon_name)
-                    .cloned()
-                    .unwrap_or_else(|| "Applications".to_string());
-                generator.set_icon_category(icon_name.clone(), category);
-            }
-            
-            for (icon_name, size_map) in size_specific_replacements {
-                let category = icon_categories.get(&icon_name)
-                    .cloned()
-                    .unwrap_or_else(|| "Applications".to_string());
-                generator.set_icon_category(icon_name.clone(), category);
-                for (size, path) in size_map {
-                    generator.add_size_replacement(icon_name.clone(), size, path);
-                    if let Some(link_map) = size_specific_links.get(&icon_name) {
-                        if let Some(&is_link) = link_map.get(&size) {
-                            generator.set_size_replacement_link(icon_name.clone(), size, is_link);
-                        }
+            });
+            self._live_worker = Some(LiveGenerationWorker::new(notify));
+        }
+        self._live_worker.as_ref().unwrap()
+    }
+
+    fn handle_live_event(&mut self, event: LiveEvent) {
+        match event {
+            LiveEvent::Started => {
+                self.generating = true;
+                self.generating_changed();
+            }
+            LiveEvent::Progress { done, total } => {
+                self.generation_progress(done as u32, total as u32);
+            }
+            // The project file already has the stored paths, keep ours in line with it
+            LiveEvent::AssetsImported { project_dir, imported } => {
+                let mut project = self._project.lock().unwrap();
+                if let Some(ref mut proj) = *project {
+                    if proj.output_path.as_ref() == Some(&project_dir) {
+                        assets::apply_imports(proj, &imported);
                     }
                 }
             }
-            
-            if let Err(e) = generator.generate() {
-                log::error!("Failed to generate theme live: {:?}", e);
-            } else {
-                log::debug!("Theme generated live to: {}", output_path.display());
+            LiveEvent::Finished(_) => {
+                self.generating = false;
+                self.generating_changed();
+                self.generation_finished();
             }
-            
-            if let Some(ref proj) = project_clone {
-                let metadata_path = output_path.join(".icon-packer-project.json");
-                if let Err(e) = proj.save(&metadata_path) {
-                    log::warn!("Failed to auto-save project metadata: {:?}", e);
-                } else {
-                    log::debug!("Project metadata auto-saved to: {:?}", metadata_path);
-                }
+            LiveEvent::Exported(output_path) => {
+                self.generating = false;
+                self.generating_changed();
+                self.generation_finished();
+                self.export_finished(QString::from(output_path.to_string_lossy().as_ref()));
             }
+            LiveEvent::Failed(message) => {
+                self.generating = false;
+                self.generating_changed();
+                self.generation_failed(message.into());
+            }
+            // A newer run starts right after, so generating stays set
+            LiveEvent::Cancelled => {}
         }
-    }),
+    }
 }
 
@@ -1,6 +1,8 @@
 use std::path::PathBuf;
 use qmetaobject::{prelude::*, QString, QVariantMap};
-use crate::icon_theme::IconThemeDefinition;
+use icon_packer_core::icon_lookup::IconLookup;
+use icon_packer_core::icon_theme::IconThemeDefinition;
+use icon_packer_core::search_paths::{icon_search_paths, load_extra_paths, save_extra_paths};
 
 #[derive(QObject, Default)]
 pub struct ThemeManager {
@@ -8,6 +10,7 @@ pub struct ThemeManager {
     available_themes: qt_property!(QVariant; NOTIFY available_themes_changed),
     available_themes_changed: qt_signal!(),
     _themes: std::sync::Arc<std::sync::Mutex<Vec<ThemeInfo>>>,
+    _lookup: std::sync::Arc<std::sync::Mutex<IconLookup>>,
     
     discover_themes: qt_method!(fn discover_themes(&mut self) {
         self.discover_themes_internal();
@@ -19,46 +22,52 @@ pub struct ThemeManager {
         names.join(",").into()
     }),
     
-    get_icon_path: qt_method!(fn get_icon_path(&self, theme_name: String, icon_name: String, size: u32) -> QString {
-        let themes = self._themes.lock().unwrap();
-        if let Some(theme) = themes.iter().find(|t| t.name == theme_name || t.directory_name == theme_name) {
-            // Try to find the icon in the theme by scanning directories
-            match crate::icon_theme::IconThemeDefinition::load_from_directory(&theme.path) {
-                Ok(theme_def) => {
-                    // Search through theme directories for the icon
-                    for directory in &theme_def.directories {
-                        let dir_path = theme_def.root_path.join(&directory.key);
-                        if !dir_path.exists() {
-                            continue;
-                        }
-                        // Try different extensions
-                        for ext in &["svg", "png", "xpm"] {
-                            let icon_path = dir_path.join(format!("{}.{}", icon_name, ext));
-                            if icon_path.exists() {
-                                // Check if size matches (for fixed/sized directories)
-                                let matches_size = match directory.dir_type {
-                                    crate::icon_theme::DirectoryType::Fixed => {
-                                        directory.size.map(|s| s == size).unwrap_or(true)
-                                    }
-                                    crate::icon_theme::DirectoryType::Scaled => {
-                                        directory.min_size.map(|min| size >= min).unwrap_or(true)
-                                            && directory.max_size.map(|max| size <= max).unwrap_or(true)
-                                    }
-                                    crate::icon_theme::DirectoryType::Threshold => {
-                                        directory.size.map(|s| (size as i32 - s as i32).abs() <= directory.threshold.unwrap_or(2) as i32).unwrap_or(true)
-                                    }
-                                };
-                                if matches_size {
-                                    return QString::from(icon_path.to_string_lossy().as_ref());
-                                }
-                            }
-                        }
-                    }
-                }
-                Err(_) => {}
-            }
+    get_search_paths: qt_method!(fn get_search_paths(&self) -> QString {
+        let paths: Vec<String> = icon_search_paths()
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+        paths.join("\n").into()
+    }),
+    
+    get_extra_search_paths: qt_method!(fn get_extra_search_paths(&self) -> QString {
+        let paths: Vec<String> = load_extra_paths()
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+        paths.join("\n").into()
+    }),
+    
+    // One path per line. Themes are discovered again, the icon catalog has to be
+    // refreshed separately.
+    set_extra_search_paths: qt_method!(fn set_extra_search_paths(&mut self, paths: QString) -> bool {
+        let paths: Vec<PathBuf> = paths.to_string()
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(PathBuf::from)
+            .collect();
+        if let Err(e) = save_extra_paths(&paths) {
+            log::error!("Failed to save search paths: {:?}", e);
+            return false;
         }
-        QString::default()
+        self.discover_themes_internal();
+        true
+    }),
+    
+    get_icon_path: qt_method!(fn get_icon_path(&self, theme_name: String, icon_name: String, size: u32, scale: u32) -> QString {
+        let directory_name = {
+            let themes = self._themes.lock().unwrap();
+            themes.iter()
+                .find(|t| t.name == theme_name || t.directory_name == theme_name)
+                .map(|t| t.directory_name.clone())
+                .unwrap_or(theme_name)
+        };
+        // Follows Inherits and falls back to hicolor like the desktop would
+        let mut lookup = self._lookup.lock().unwrap();
+        lookup.find_icon(&icon_name, size, scale.max(1), &directory_name)
+            .map(|path| QString::from(path.to_string_lossy().as_ref()))
+            .unwrap_or_default()
     }),
 }
 
@@ -66,6 +75,7 @@ pub struct ThemeManager {
 struct ThemeInfo {
     name: String,
     directory_name: String,
+    #[allow(dead_code)]
     path: PathBuf,
     #[allow(dead_code)]
     inherits: Vec<String>,
@@ -73,20 +83,22 @@ struct ThemeInfo {
 
 impl ThemeManager {
     fn discover_themes_internal(&mut self) {
-        let mut themes = Vec::new();
-        let roots = icon_base_dirs();
+        let mut themes: Vec<ThemeInfo> = Vec::new();
+        let roots = icon_search_paths();
         
-        for root in roots {
+        for root in &roots {
             if !root.exists() {
                 continue;
             }
-            if let Ok(entries) = std::fs::read_dir(&root) {
+            if let Ok(entries) = std::fs::read_dir(root) {
                 for entry in entries.flatten() {
                     let path = entry.path();
                     if !path.is_dir() {
                         continue;
                     }
                     match IconThemeDefinition::load_from_directory(&path) {
+                        // Earlier search paths shadow themes of the same name in later ones
+                        Ok(theme) if themes.iter().any(|t| t.directory_name == theme.directory_name) => {}
                         Ok(theme) => {
                             themes.push(ThemeInfo {
                                 name: theme.name.clone(),
@@ -105,6 +117,7 @@ impl ThemeManager {
         
         themes.sort_by(|a, b| a.name.cmp(&b.name));
         *self._themes.lock().unwrap() = themes.clone();
+        *self._lookup.lock().unwrap() = IconLookup::new(roots);
         
         let mut map = QVariantMap::default();
         for theme in themes {
@@ -117,28 +130,3 @@ impl ThemeManager {
         self.available_themes_changed();
     }
 }
-
-fn icon_base_dirs() -> Vec<PathBuf> {
-    let mut dirs = Vec::new();
-    
-    // User's local icons
-    if let Ok(home) = std::env::var("HOME") {
-        let home_path = PathBuf::from(&home);
-        dirs.push(home_path.join(".icons"));
-        dirs.push(PathBuf::from(home).join(".local/share/icons"));
-    }
-    
-    // System-wide icons
-    if let Ok(xdg_dirs) = std::env::var("XDG_DATA_DIRS") {
-        for dir in xdg_dirs.split(':') {
-            dirs.push(PathBuf::from(dir).join("icons"));
-        }
-    }
-    
-    // Fallback system paths
-    dirs.push(PathBuf::from("/usr/share/icons"));
-    dirs.push(PathBuf::from("/usr/local/share/icons"));
-    
-    dirs
-}
-